@@ -1,5 +1,7 @@
 pub mod types {
+    pub mod build_environment_info;
     pub mod contract_source_metadata;
+    pub mod image_reference;
     pub mod source_id;
     pub mod whitelist;
 
@@ -28,28 +30,69 @@ pub mod logic {
         tracing::debug!("cli_build_command_in_docker {:#?}", build_command);
         shell_words::join(build_command)
     }
+    pub mod container_cleanup;
+    pub mod mirror;
     pub mod nep330_build;
+    pub mod path_mapping;
+    pub mod platform;
+    pub mod repo_mount;
+    pub mod spans;
+    pub mod userns;
+    pub mod workdir_provenance;
     pub mod docker_checks {
         use crate::logic::internal::docker_command::handle_io_error;
         use crate::logic::internal::docker_command::print;
 
+        pub mod environment;
+        pub mod inspect;
+        pub mod labels;
+        pub mod load_image;
         pub mod pull_image;
         pub mod sanity;
+        pub mod shell;
+        #[cfg(feature = "signature")]
+        pub mod signature;
     }
 
     pub(crate) mod internal {
         pub mod docker_command;
+        #[cfg(unix)]
+        pub mod engine_api;
+        pub mod git;
+        pub mod oci_layout;
+        #[cfg(feature = "signature")]
+        pub mod registry;
+    }
+    /// `<timestamp>-<pid>` suffix, unique across processes and calls
+    pub(crate) fn unique_suffix() -> String {
+        // Cross-platform process ID and timestamp
+        let pid = std::process::id().to_string();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+            .to_string();
+        format!("{}-{}", timestamp, pid)
     }
     pub fn compute_hash(
         path: camino::Utf8PathBuf,
     ) -> eyre::Result<crate::types::sha256_checksum::SHA256Checksum> {
+        let span = spans::hash(&path);
+        let _guard = span.enter();
+        let started = std::time::Instant::now();
+
         let mut hasher = <sha2::Sha256 as sha2::Digest>::new();
         sha2::Digest::update(&mut hasher, std::fs::read(&path)?);
         let hash = sha2::Digest::finalize(hasher);
         let hash: &[u8] = hash.as_ref();
-        Ok(crate::types::sha256_checksum::SHA256Checksum {
+        let checksum = crate::types::sha256_checksum::SHA256Checksum {
             hash: hash.to_vec(),
-        })
+        };
+
+        span.record("checksum_hex", checksum.to_hex_string());
+        span.record("checksum_base58", checksum.to_base58_string());
+        spans::record_duration(&span, started);
+        Ok(checksum)
     }
 }
 
@@ -64,22 +107,11 @@ pub mod pretty_print {
             .ok();
         indented_string
     }
-
-    macro_rules! quiet_println {
-        ($quiet: expr) => {
-            if !$quiet {
-                println!();
-            }
-        };
-        ($quiet: expr, $($arg:tt)*) => {{
-            if !$quiet {
-                println!($($arg)*);
-            }
-        }};
-    }
-    pub(crate) use quiet_println;
 }
 
+/// pluggable sink of user-facing output
+pub mod reporter;
+
 /// module contains variables, which are set to configure build with WASM reproducibility,
 /// which correspond to some fields of `ContractSourceMetadata` in <https://github.com/near/NEPs/blob/master/neps/nep-0330.md>
 pub mod env_keys;
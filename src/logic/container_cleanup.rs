@@ -0,0 +1,411 @@
+//! Removal of build containers, which outlive the verifier process otherwise, e.g. when
+//! it's interrupted during `docker run`.
+//!
+//! Every build container is labeled with [REV], [IMAGE], [PID] and [HOST], and is held by
+//! a guard, which removes it on drop, including unwinding of a panic. Containers are removed
+//! on `SIGINT`/`SIGTERM` only if the application opts in with [install_signal_handler],
+//! or calls [remove_active_containers] from its own handler.
+//! Containers of a verifier, which has crashed or was killed with `SIGKILL`, are removed
+//! by [reap_stale_containers].
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::logic::nep330_build::Backend;
+use crate::reporter::{Message, Reporter};
+
+/// version of near-verify-rs, which has created the container
+pub const REV: &str = "near-verify-rs.rev";
+/// build environment the container is created from, e.g. `sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:...`
+pub const IMAGE: &str = "near-verify-rs.image";
+/// id of the verifier process, which has created the container
+pub const PID: &str = "near-verify-rs.pid";
+/// [host_id] of the verifier process, which has created the container
+pub const HOST: &str = "near-verify-rs.host";
+
+/// names of containers of live [Guard]s, with backend they are removed with
+static ACTIVE: Mutex<BTreeMap<String, Backend>> = Mutex::new(BTreeMap::new());
+
+/// identity of the pid namespace the current process is in, which [PID] is relative to:
+/// boot id and pid namespace on linux, hostname elsewhere; [None] if it cannot be determined
+pub fn host_id() -> Option<String> {
+    static HOST_ID: std::sync::LazyLock<Option<String>> = std::sync::LazyLock::new(|| {
+        let host_id = read_host_id();
+        if let Err(ref err) = host_id {
+            tracing::debug!("host id isn't determined: {:?}", err);
+        }
+        host_id.ok()
+    });
+    HOST_ID.clone()
+}
+
+#[cfg(target_os = "linux")]
+fn read_host_id() -> eyre::Result<String> {
+    let boot_id = std::fs::read_to_string("/proc/sys/kernel/random/boot_id")?;
+    let pid_namespace = std::fs::read_link("/proc/self/ns/pid")?;
+    Ok(format!("{}/{}", boot_id.trim(), pid_namespace.display()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_host_id() -> eyre::Result<String> {
+    let output = std::process::Command::new("hostname").output()?;
+    let hostname = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || hostname.is_empty() {
+        return Err(eyre::eyre!("`hostname` failed with {}", output.status));
+    }
+    Ok(hostname)
+}
+
+/// labels of a container of `build_environment`, created by the current process
+pub(crate) fn labels(build_environment: &str) -> Vec<(&'static str, String)> {
+    let mut labels = vec![
+        (REV, env!("CARGO_PKG_VERSION").to_string()),
+        (IMAGE, build_environment.to_string()),
+        (PID, std::process::id().to_string()),
+    ];
+    if let Some(host_id) = host_id() {
+        labels.push((HOST, host_id));
+    }
+    labels
+}
+
+/// removes container `name`; a container, which doesn't exist (anymore), isn't an error
+fn remove(name: &str, backend: &Backend) -> eyre::Result<()> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        return match client.remove_container(name) {
+            Err(err)
+                if err
+                    .downcast_ref::<crate::logic::internal::engine_api::ApiError>()
+                    .is_some_and(|err| err.status == 404) =>
+            {
+                Ok(())
+            }
+            result => result,
+        };
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+
+    let output = std::process::Command::new("docker")
+        .args(["rm", "--force", name])
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && !is_no_such_container(&stderr) {
+        return Err(eyre::eyre!(
+            "`docker rm --force {}` failed with {}: {}",
+            name,
+            output.status,
+            stderr.trim()
+        ));
+    }
+    Ok(())
+}
+
+/// `Error response from daemon: No such container: <name>` of `docker rm`
+fn is_no_such_container(stderr: &str) -> bool {
+    stderr.to_lowercase().contains("no such container")
+}
+
+fn active() -> std::sync::MutexGuard<'static, BTreeMap<String, Backend>> {
+    ACTIVE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// removes containers of live guards, i.e. of builds in progress, to be called from a signal
+/// handler of an application, which doesn't use [install_signal_handler]
+pub fn remove_active_containers() {
+    let containers = std::mem::take(&mut *active());
+    for (name, backend) in containers {
+        match remove(&name, &backend) {
+            Ok(()) => tracing::info!("removed container `{}`", name),
+            Err(err) => tracing::warn!("failed to remove container `{}`: {:?}", name, err),
+        }
+    }
+}
+
+/// opts in to [remove_active_containers] on `SIGINT`/`SIGTERM`, after which the signal
+/// is handled the default way, i.e. the process is terminated; meant for command line
+/// applications without signal handling of their own, it's a no-op on a second call
+#[cfg(unix)]
+pub fn install_signal_handler() -> eyre::Result<()> {
+    static INSTALLED: std::sync::Mutex<bool> = std::sync::Mutex::new(false);
+    let mut installed = INSTALLED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if *installed {
+        return Ok(());
+    }
+    use signal_hook::consts::{SIGINT, SIGTERM};
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM])?;
+    std::thread::Builder::new()
+        .name("near-verify-rs-container-cleanup".to_string())
+        .spawn(move || {
+            for signal in signals.forever() {
+                remove_active_containers();
+                if let Err(err) = signal_hook::low_level::emulate_default_handler(signal) {
+                    tracing::warn!("failed to terminate on signal {}: {:?}", signal, err);
+                }
+            }
+        })?;
+    *installed = true;
+    Ok(())
+}
+
+/// removes container `name` on drop, unless it's [Guard::removed] by other means;
+/// it's registered for [remove_active_containers] while alive
+#[must_use]
+pub(crate) struct Guard {
+    name: String,
+    backend: Backend,
+}
+
+impl Guard {
+    /// to be created before the container itself, so that it's removed on a signal,
+    /// which arrives in between
+    pub fn new(name: String, backend: Backend) -> Self {
+        active().insert(name.clone(), backend.clone());
+        Self { name, backend }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// container is already removed, e.g. by `docker run --rm`
+    pub fn removed(&self) {
+        active().remove(&self.name);
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if active().remove(&self.name).is_none() {
+            return;
+        }
+        if let Err(err) = remove(&self.name, &self.backend) {
+            tracing::debug!("failed to remove container `{}`: {:?}", self.name, err);
+        }
+    }
+}
+
+/// container, labeled with [PID], with values of [PID] and [HOST] labels
+#[derive(Debug, PartialEq, Eq)]
+struct Labeled {
+    name: String,
+    pid: Option<u32>,
+    host: Option<String>,
+}
+
+impl Labeled {
+    /// created on the same host as the current process, by a process, which doesn't exist
+    fn is_stale(&self, host_id: &str) -> bool {
+        self.host.as_deref() == Some(host_id)
+            && self
+                .pid
+                .is_some_and(|pid| pid != std::process::id() && !is_alive(pid))
+    }
+}
+
+/// `docker ps --format` output of [list_labeled] with container name, its [PID] and [HOST] labels
+fn parse_ps(stdout: &str) -> Vec<Labeled> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.trim();
+            let pid = fields.next()?.trim().parse().ok();
+            let host = fields
+                .next()
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(str::to_string);
+            Some(Labeled {
+                name: name.to_string(),
+                pid,
+                host,
+            })
+        })
+        .collect()
+}
+
+fn list_labeled(backend: &Backend) -> eyre::Result<Vec<Labeled>> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        return Ok(client
+            .list_containers(PID)?
+            .iter()
+            .filter_map(|container| {
+                let name = container["Names"][0].as_str()?.trim_start_matches('/');
+                let label = |label: &str| container["Labels"][label].as_str();
+                Some(Labeled {
+                    name: name.to_string(),
+                    pid: label(PID).and_then(|pid| pid.parse().ok()),
+                    host: label(HOST).map(str::to_string),
+                })
+            })
+            .collect());
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+
+    let output = std::process::Command::new("docker")
+        .args([
+            "ps",
+            "--all",
+            "--filter",
+            &format!("label={}", PID),
+            "--format",
+            &format!(
+                "{{{{.Names}}}}\t{{{{.Label \"{}\"}}}}\t{{{{.Label \"{}\"}}}}",
+                PID, HOST
+            ),
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`docker ps` failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_ps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(target_os = "linux")]
+fn is_alive(pid: u32) -> bool {
+    std::path::Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("ps")
+        .args(["-p", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// liveness of other processes isn't checked, so that their containers are never removed
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+/// result of [reap_stale_containers]
+#[derive(Debug, Default)]
+pub struct Reaped {
+    pub removed: Vec<String>,
+    /// containers, which failed to be removed, with errors of their removal
+    pub failed: Vec<(String, eyre::Report)>,
+}
+
+/// removes containers of verifier processes, which don't exist anymore; only containers
+/// with [HOST] label of the current [host_id] are considered, so that builds of verifiers
+/// on other hosts or in other pid namespaces, sharing the docker daemon, are left intact.
+/// A failure to remove a container is reported and doesn't stop removal of others
+pub fn reap_stale_containers(backend: &Backend, reporter: &dyn Reporter) -> eyre::Result<Reaped> {
+    let mut reaped = Reaped::default();
+    let Some(host_id) = host_id() else {
+        reporter.report(
+            Message::new().warning("host id isn't determined, no containers are considered stale"),
+        );
+        return Ok(reaped);
+    };
+    for container in list_labeled(backend)? {
+        if !container.is_stale(&host_id) {
+            continue;
+        }
+        match remove(&container.name, backend) {
+            Ok(()) => {
+                reporter.report(
+                    Message::new()
+                        .title("removed stale container:")
+                        .normal(container.name.as_str()),
+                );
+                reaped.removed.push(container.name);
+            }
+            Err(err) => {
+                reporter.report(Message::new().warning(format!(
+                    "failed to remove stale container `{}`: {}",
+                    container.name, err
+                )));
+                reaped.failed.push((container.name, err));
+            }
+        }
+    }
+    Ok(reaped)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_ps() {
+        let stdout = "near-verify-rs-1-42\t42\tboot/pid:[1]\nnear-verify-rs-2-43\tgarbage\t\n\n";
+        assert_eq!(
+            super::parse_ps(stdout),
+            vec![
+                super::Labeled {
+                    name: "near-verify-rs-1-42".to_string(),
+                    pid: Some(42),
+                    host: Some("boot/pid:[1]".to_string()),
+                },
+                super::Labeled {
+                    name: "near-verify-rs-2-43".to_string(),
+                    pid: None,
+                    host: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let labeled = |pid, host: &str| super::Labeled {
+            name: "near-verify-rs-1-42".to_string(),
+            pid: Some(pid),
+            host: Some(host.to_string()),
+        };
+        let dead = u32::MAX - 1;
+        #[cfg(unix)]
+        assert!(labeled(dead, "this").is_stale("this"));
+        // another host or pid namespace
+        assert!(!labeled(dead, "other").is_stale("this"));
+        assert!(!labeled(std::process::id(), "this").is_stale("this"));
+        assert!(
+            !super::Labeled {
+                host: None,
+                ..labeled(dead, "this")
+            }
+            .is_stale("this")
+        );
+    }
+
+    #[test]
+    fn test_no_such_container() {
+        assert!(super::is_no_such_container(
+            "Error response from daemon: No such container: near-verify-rs-1-42"
+        ));
+        assert!(!super::is_no_such_container(
+            "Error response from daemon: permission denied"
+        ));
+    }
+
+    #[test]
+    fn test_is_alive() {
+        assert!(super::is_alive(std::process::id()));
+        #[cfg(unix)]
+        assert!(!super::is_alive(u32::MAX - 1));
+    }
+
+    #[test]
+    fn test_guard_without_container() {
+        let name = format!("near-verify-rs-test-{}", crate::logic::unique_suffix());
+        let guard = super::Guard::new(name.clone(), Default::default());
+        assert!(super::active().contains_key(&name));
+        guard.removed();
+        drop(guard);
+        assert!(!super::active().contains_key(&name));
+    }
+}
@@ -0,0 +1,447 @@
+//! Diagnostics of docker daemon, queried with `docker info` or `GET /info` of
+//! [Backend::EngineApi], which needs neither network access nor any image
+use serde::Deserialize;
+
+use crate::logic::nep330_build::Backend;
+use crate::reporter::{Message, Reporter};
+
+const ERR_DIAGNOSTICS: &str = "`docker` environment diagnostics failed!";
+
+/// Container runtime behind `docker` CLI or docker engine API socket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Runtime {
+    #[default]
+    Docker,
+    /// e.g. `docker` alias of `podman-docker` package, or podman's docker compatible API socket
+    Podman,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DockerEnvironment {
+    pub runtime: Runtime,
+    pub server_version: String,
+    /// `linux` or `windows`
+    pub os_type: String,
+    /// machine of docker daemon's host, as reported by `uname -m`, e.g. `x86_64` or `aarch64`
+    pub architecture: String,
+    pub rootless: bool,
+    /// `1` or `2`, [None] if not reported
+    pub cgroup_version: Option<String>,
+    pub storage_driver: String,
+    pub selinux: bool,
+    pub apparmor: bool,
+    /// all security options of the daemon, e.g. `name=seccomp,profile=builtin`
+    pub security_options: Vec<String>,
+}
+
+/// subset of response of `GET /info`, which is also `docker info --format '{{json .}}'`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Info {
+    #[serde(default)]
+    server_version: String,
+    #[serde(default, rename = "OSType")]
+    os_type: String,
+    #[serde(default)]
+    architecture: String,
+    #[serde(default)]
+    cgroup_version: Option<String>,
+    #[serde(default)]
+    driver: String,
+    #[serde(default)]
+    security_options: Option<Vec<String>>,
+}
+
+/// subset of `podman info --format json`, which is what `docker info` of `podman-docker` outputs
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PodmanInfo {
+    host: PodmanHost,
+    #[serde(default)]
+    store: PodmanStore,
+    #[serde(default)]
+    version: PodmanVersion,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PodmanHost {
+    #[serde(default)]
+    arch: String,
+    #[serde(default)]
+    os: String,
+    /// `v1` or `v2`
+    #[serde(default)]
+    cgroup_version: String,
+    #[serde(default)]
+    security: PodmanSecurity,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PodmanSecurity {
+    #[serde(default)]
+    rootless: bool,
+    #[serde(default)]
+    selinux_enabled: bool,
+    #[serde(default)]
+    apparmor_enabled: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PodmanStore {
+    #[serde(default)]
+    graph_driver_name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PodmanVersion {
+    #[serde(default)]
+    version: String,
+}
+
+impl DockerEnvironment {
+    /// `info` in either docker's or podman's native format; `podman` is whether docker
+    /// compatible `info` is known to be served by podman
+    fn from_value(info: serde_json::Value, podman: bool) -> eyre::Result<Self> {
+        if info.get("host").is_some() {
+            return Ok(Self::from_podman_info(serde_json::from_value(info)?));
+        }
+        let runtime = match podman {
+            true => Runtime::Podman,
+            false => Runtime::Docker,
+        };
+        Ok(Self::from_info(serde_json::from_value(info)?, runtime))
+    }
+
+    fn from_podman_info(info: PodmanInfo) -> Self {
+        let security = &info.host.security;
+        let mut security_options = vec![];
+        for (enabled, name) in [
+            (security.rootless, "rootless"),
+            (security.selinux_enabled, "selinux"),
+            (security.apparmor_enabled, "apparmor"),
+        ] {
+            if enabled {
+                security_options.push(format!("name={}", name));
+            }
+        }
+        Self {
+            runtime: Runtime::Podman,
+            server_version: info.version.version,
+            os_type: info.host.os,
+            architecture: info.host.arch,
+            rootless: security.rootless,
+            cgroup_version: Some(info.host.cgroup_version.trim_start_matches('v').to_string())
+                .filter(|version| !version.is_empty()),
+            storage_driver: info.store.graph_driver_name,
+            selinux: security.selinux_enabled,
+            apparmor: security.apparmor_enabled,
+            security_options,
+        }
+    }
+
+    fn from_info(info: Info, runtime: Runtime) -> Self {
+        let security_options = info.security_options.unwrap_or_default();
+        // `name=seccomp,profile=builtin` option has `seccomp` name
+        let has_option = |name: &str| {
+            security_options.iter().any(|option| {
+                option
+                    .split(',')
+                    .any(|pair| pair == format!("name={}", name))
+            })
+        };
+        Self {
+            runtime,
+            server_version: info.server_version,
+            os_type: info.os_type,
+            architecture: info.architecture,
+            rootless: has_option("rootless"),
+            cgroup_version: info.cgroup_version.filter(|version| !version.is_empty()),
+            storage_driver: info.driver,
+            selinux: has_option("selinux"),
+            apparmor: has_option("apparmor"),
+            security_options,
+        }
+    }
+}
+
+/// Cause of failure to reach docker daemon, with its remediation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// current user isn't allowed to access docker socket
+    PermissionDenied,
+    /// current docker context or `DOCKER_HOST` refers to something, that doesn't exist
+    ContextMisconfigured,
+    /// docker daemon isn't running
+    DaemonDown,
+    Other,
+}
+
+impl Problem {
+    /// classifies error output of `docker` CLI or an error of docker engine API
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        if error.contains("permission denied") {
+            Self::PermissionDenied
+        } else if error.contains("context")
+            && ["not found", "does not exist", "failed to"]
+                .iter()
+                .any(|pattern| error.contains(pattern))
+        {
+            Self::ContextMisconfigured
+        } else if [
+            "cannot connect to the docker daemon",
+            "is the docker daemon running",
+            "cannot connect to docker engine",
+            "error during connect",
+            "connection refused",
+        ]
+        .iter()
+        .any(|pattern| error.contains(pattern))
+            // a missing socket, rather than any missing file
+            || (error.contains("no such file or directory")
+                && ["docker.sock", "unix://"]
+                    .iter()
+                    .any(|pattern| error.contains(pattern)))
+        {
+            Self::DaemonDown
+        } else {
+            Self::Other
+        }
+    }
+
+    pub fn report_hints(&self, reporter: &dyn Reporter) {
+        match self {
+            Self::PermissionDenied => {
+                reporter.report(Message::new().hint("Permission denied!"));
+                super::print::linux_postinstall_steps(reporter);
+            }
+            Self::ContextMisconfigured => {
+                reporter.report(Message::new().hint(
+                    "Current docker context or `DOCKER_HOST` refers to an unavailable daemon. \
+                    Please, check `docker context ls`, switch with `docker context use default` \
+                    or unset `DOCKER_CONTEXT`/`DOCKER_HOST`",
+                ));
+            }
+            Self::DaemonDown => {
+                reporter.report(Message::new().hint(
+                    "Docker daemon isn't running. Please, start it with `sudo systemctl start docker`, \
+                    `systemctl --user start docker` for rootless docker, or start Docker Desktop",
+                ));
+            }
+            Self::Other => super::print::installation_links(reporter),
+        }
+    }
+}
+
+fn info_on(backend: &Backend, reporter: &dyn Reporter) -> eyre::Result<DockerEnvironment> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        return match client.info() {
+            Ok(info) => {
+                // podman's docker compatible API is recognized by components of its version
+                let podman = client.version().is_ok_and(|version| {
+                    version["Components"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .any(|component| {
+                            component["Name"]
+                                .as_str()
+                                .is_some_and(|name| name.contains("Podman"))
+                        })
+                });
+                DockerEnvironment::from_value(info, podman)
+            }
+            Err(err) => {
+                reporter.separator();
+                reporter.report(Message::new().warning(format!("{:#}", err)));
+                Problem::classify(&format!("{:?}", err)).report_hints(reporter);
+                Err(err.wrap_err(ERR_DIAGNOSTICS))
+            }
+        };
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.args(["info", "--format", "{{json .}}"]);
+    let output_result = docker_cmd.output();
+    let output = super::handle_io_error(
+        &docker_cmd,
+        output_result,
+        eyre::eyre!(ERR_DIAGNOSTICS),
+        reporter,
+    )?;
+    let info = serde_json::from_slice::<serde_json::Value>(&output.stdout).ok();
+    // errors of `docker info`, e.g. when daemon is unreachable
+    let server_errors = info
+        .as_ref()
+        .and_then(|info| info["ServerErrors"].as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|error| error.as_str().map(str::to_string))
+        .collect::<Vec<_>>();
+    match info {
+        Some(info) if output.status.success() && server_errors.is_empty() => {
+            DockerEnvironment::from_value(info, false)
+        }
+        _ => {
+            let error = [
+                server_errors.join("\n"),
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ]
+            .join("\n");
+            reporter.separator();
+            reporter.report(Message::new().warning(error.trim()));
+            Problem::classify(&error).report_hints(reporter);
+            super::print::command_status(output.status, docker_cmd, reporter);
+            Err(eyre::eyre!(ERR_DIAGNOSTICS))
+        }
+    }
+}
+
+/// [DockerEnvironment] of docker daemon of `backend`, without reporting anything
+pub fn query(backend: &Backend) -> eyre::Result<DockerEnvironment> {
+    info_on(backend, &crate::reporter::Silent)
+}
+
+/// queries docker daemon for [DockerEnvironment], reporting it; a failure to reach the daemon
+/// is reported with hints on remediation of its [Problem]
+pub fn diagnose(backend: &Backend, reporter: &dyn Reporter) -> eyre::Result<DockerEnvironment> {
+    let environment = info_on(backend, reporter)?;
+    let cgroup_version = environment.cgroup_version.as_deref().unwrap_or("unknown");
+    let runtime = match environment.runtime {
+        Runtime::Docker => "docker",
+        Runtime::Podman => "podman",
+    };
+    reporter.report(
+        Message::new()
+            .title("docker server:")
+            .normal(format!(
+                "{} {} {}/{}",
+                runtime, environment.server_version, environment.os_type, environment.architecture
+            ))
+            .title("rootless:")
+            .normal(environment.rootless.to_string())
+            .title("cgroup:")
+            .normal(format!("v{}", cgroup_version))
+            .title("storage driver:")
+            .normal(environment.storage_driver.as_str())
+            .title("selinux:")
+            .normal(environment.selinux.to_string())
+            .title("apparmor:")
+            .normal(environment.apparmor.to_string()),
+    );
+    Ok(environment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DockerEnvironment, Info, Problem, Runtime};
+
+    #[test]
+    fn test_from_info() -> eyre::Result<()> {
+        let info: Info = serde_json::from_value(serde_json::json!({
+            "ServerVersion": "27.3.1",
+            "OSType": "linux",
+            "Architecture": "aarch64",
+            "CgroupVersion": "2",
+            "Driver": "overlay2",
+            "SecurityOptions": [
+                "name=seccomp,profile=builtin",
+                "name=rootless",
+                "name=cgroupns"
+            ],
+            "Containers": 3,
+        }))?;
+        let environment = DockerEnvironment::from_info(info, Runtime::Docker);
+        assert_eq!(environment.server_version, "27.3.1");
+        assert_eq!(environment.architecture, "aarch64");
+        assert!(environment.rootless);
+        assert!(!environment.selinux);
+        assert!(!environment.apparmor);
+        assert_eq!(environment.cgroup_version.as_deref(), Some("2"));
+        assert_eq!(environment.storage_driver, "overlay2");
+
+        let info: Info = serde_json::from_value(serde_json::json!({
+            "ServerVersion": "20.10.7",
+            "SecurityOptions": ["name=selinux", "name=apparmor"],
+        }))?;
+        let environment = DockerEnvironment::from_info(info, Runtime::Docker);
+        assert!(environment.selinux && environment.apparmor && !environment.rootless);
+        assert_eq!(environment.cgroup_version, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_podman_info() -> eyre::Result<()> {
+        let info = serde_json::json!({
+            "host": {
+                "arch": "amd64",
+                "os": "linux",
+                "cgroupVersion": "v2",
+                "security": {"rootless": true, "selinuxEnabled": true, "apparmorEnabled": false},
+            },
+            "store": {"graphDriverName": "overlay"},
+            "version": {"Version": "4.9.3"},
+        });
+        let environment = DockerEnvironment::from_value(info, false)?;
+        assert_eq!(environment.runtime, Runtime::Podman);
+        assert_eq!(environment.server_version, "4.9.3");
+        assert!(environment.rootless && environment.selinux && !environment.apparmor);
+        assert_eq!(environment.cgroup_version.as_deref(), Some("2"));
+        assert_eq!(environment.storage_driver, "overlay");
+
+        let compat =
+            serde_json::json!({"ServerVersion": "4.9.3", "SecurityOptions": ["name=rootless"]});
+        let environment = DockerEnvironment::from_value(compat, true)?;
+        assert_eq!(environment.runtime, Runtime::Podman);
+        assert!(environment.rootless);
+        Ok(())
+    }
+
+    #[test]
+    fn test_classify() {
+        for (error, problem) in [
+            (
+                "permission denied while trying to connect to the Docker daemon socket at unix:///var/run/docker.sock",
+                Problem::PermissionDenied,
+            ),
+            (
+                "Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?",
+                Problem::DaemonDown,
+            ),
+            (
+                "cannot connect to docker engine at `/var/run/docker.sock`: Connection refused (os error 111)",
+                Problem::DaemonDown,
+            ),
+            (
+                "current context \"remote\" is not found on the file system",
+                Problem::ContextMisconfigured,
+            ),
+            (
+                "context \"staging\": context not found: open /root/.docker/contexts/meta/0/meta.json: no such file or directory",
+                Problem::ContextMisconfigured,
+            ),
+            (
+                "dial unix /var/run/docker.sock: connect: no such file or directory",
+                Problem::DaemonDown,
+            ),
+            (
+                "error checking context: unix:///run/user/1000/podman/podman.sock: no such file or directory",
+                Problem::DaemonDown,
+            ),
+            (
+                "open /tmp/build/Dockerfile: no such file or directory",
+                Problem::Other,
+            ),
+            ("unexpected", Problem::Other),
+        ] {
+            assert_eq!(Problem::classify(error), problem, "{}", error);
+        }
+    }
+}
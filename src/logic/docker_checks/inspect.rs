@@ -0,0 +1,187 @@
+//! Inspection of a pulled image, confirming it's the one pinned in `build_environment`
+use std::collections::BTreeMap;
+
+use eyre::WrapErr;
+use serde::Deserialize;
+
+pub use crate::logic::platform::Platform;
+use crate::reporter::{Message, Reporter};
+use crate::types::image_reference::ImageReference;
+
+/// Local image, as reported by `docker image inspect`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct InspectedImage {
+    /// `sha256:<hex>` content-addressable id of the local image, which can be passed
+    /// to `docker run` instead of a reference
+    pub id: String,
+    #[serde(default)]
+    pub repo_digests: Vec<String>,
+    pub os: String,
+    pub architecture: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+    #[serde(default)]
+    pub config: Option<ImageConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImageConfig {
+    #[serde(default)]
+    pub labels: Option<BTreeMap<String, String>>,
+}
+
+impl InspectedImage {
+    pub fn platform(&self) -> Platform {
+        Platform {
+            os: self.os.clone(),
+            architecture: self.architecture.clone(),
+            variant: self.variant.clone().filter(|variant| !variant.is_empty()),
+        }
+    }
+
+    /// labels of the image's config, e.g. [super::labels::SOURCE]
+    pub fn labels(&self) -> BTreeMap<String, String> {
+        self.config
+            .as_ref()
+            .and_then(|config| config.labels.clone())
+            .unwrap_or_default()
+    }
+
+    /// checks that [Self::repo_digests] contain digest of `pinned` for its repository,
+    /// and that [Self::platform] is one of `allowed_platforms`
+    pub fn verify(
+        &self,
+        pinned: &ImageReference,
+        allowed_platforms: &[Platform],
+    ) -> eyre::Result<()> {
+        let pinned_digest = pinned
+            .digest
+            .as_deref()
+            .ok_or_else(|| eyre::eyre!("`{}` isn't pinned with a digest", pinned))?;
+        let matches = self.repo_digests.iter().any(|repo_digest| {
+            repo_digest
+                .parse::<ImageReference>()
+                .map(|repo_digest| {
+                    repo_digest.name() == pinned.name()
+                        && repo_digest.digest.as_deref() == Some(pinned_digest)
+                })
+                .unwrap_or(false)
+        });
+        if !matches {
+            return Err(eyre::eyre!(
+                "local image `{}` has repo digests {:?}, none of which is pinned `{}@{}`",
+                self.id,
+                self.repo_digests,
+                pinned.familiar_name(),
+                pinned_digest
+            ));
+        }
+
+        self.verify_platform(&pinned.familiar_name(), allowed_platforms)
+    }
+
+    /// checks that [Self::platform] of `image` is one of `allowed_platforms`
+    pub fn verify_platform(&self, image: &str, allowed_platforms: &[Platform]) -> eyre::Result<()> {
+        let platform = self.platform();
+        if !allowed_platforms
+            .iter()
+            .any(|allowed| platform.is_allowed_by(allowed))
+        {
+            return Err(eyre::eyre!(
+                "image `{}` is built for `{}`, which isn't one of allowed platforms: {}",
+                image,
+                platform,
+                allowed_platforms
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn inspect(docker_image: &str) -> eyre::Result<InspectedImage> {
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.args(["image", "inspect", "--format", "{{json .}}", docker_image]);
+    tracing::debug!("docker command: {:?}", docker_cmd);
+    let output = docker_cmd
+        .output()
+        .map_err(|err| eyre::eyre!("failed to execute `{:?}`: {:?}", docker_cmd, err))?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`{:?}` failed with {}: {}",
+            docker_cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout)
+        .wrap_err_with(|| format!("malformed output of `{:?}`", docker_cmd))
+}
+
+/// [inspect] and [InspectedImage::verify] of `docker_image`, reporting its id
+pub fn verify(
+    docker_image: &str,
+    allowed_platforms: &[Platform],
+    reporter: &dyn Reporter,
+) -> eyre::Result<InspectedImage> {
+    let pinned: ImageReference = docker_image.parse()?;
+    let inspected = inspect(docker_image)?;
+    inspected.verify(&pinned, allowed_platforms)?;
+    reporter.report(
+        Message::new()
+            .title("local image id:")
+            .normal(inspected.id.as_str())
+            .title("platform:")
+            .normal(inspected.platform().to_string()),
+    );
+    Ok(inspected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InspectedImage, Platform};
+
+    const DIGEST: &str = "sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+
+    fn inspected(repo_digests: &[&str], architecture: &str) -> InspectedImage {
+        serde_json::from_value(serde_json::json!({
+            "Id": "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+            "RepoDigests": repo_digests,
+            "Os": "linux",
+            "Architecture": architecture,
+            "Size": 42,
+        }))
+        .expect("no error")
+    }
+
+    #[test]
+    fn test_verify() -> eyre::Result<()> {
+        let pinned = format!("sourcescan/cargo-near:0.13.4-rust-1.85.0@{}", DIGEST).parse()?;
+        let amd64 = [Platform::linux_amd64()];
+
+        let image = inspected(&[&format!("sourcescan/cargo-near@{}", DIGEST)], "amd64");
+        assert!(image.verify(&pinned, &amd64).is_ok());
+
+        let other_digest =
+            "sha256:2320519772d04dd960c2c5c0172c0887ca4407e1c7c04e3be246b07cc5b21db0";
+        let image = inspected(
+            &[&format!("sourcescan/cargo-near@{}", other_digest)],
+            "amd64",
+        );
+        assert!(image.verify(&pinned, &amd64).is_err());
+
+        let image = inspected(&[&format!("mirror.io/cargo-near@{}", DIGEST)], "amd64");
+        assert!(image.verify(&pinned, &amd64).is_err());
+
+        let image = inspected(&[&format!("sourcescan/cargo-near@{}", DIGEST)], "arm64");
+        let err = image.verify(&pinned, &amd64).unwrap_err();
+        assert!(err.to_string().contains("is built for `linux/arm64`"));
+        assert!(image.verify(&pinned, &["linux/arm64".parse()?]).is_ok());
+        Ok(())
+    }
+}
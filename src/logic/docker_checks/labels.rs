@@ -0,0 +1,175 @@
+//! Provenance of a build environment, declared with [OCI annotations](https://github.com/opencontainers/image-spec/blob/main/annotations.md)
+//! as labels of the image, e.g. repository and commit `sourcescan/cargo-near` images are built from
+use std::collections::BTreeMap;
+
+use super::inspect::InspectedImage;
+use crate::logic::nep330_build::Backend;
+use crate::logic::spans;
+use crate::reporter::{Message, Reporter};
+
+/// URL of the repository the image is built from
+pub const SOURCE: &str = "org.opencontainers.image.source";
+/// revision of [SOURCE] the image is built from
+pub const REVISION: &str = "org.opencontainers.image.revision";
+
+/// `https://github.com/near/cargo-near.git/` is `github.com/near/cargo-near`
+fn normalize_source(source: &str) -> &str {
+    let source = ["git+https://", "https://", "http://", "git://"]
+        .iter()
+        .find_map(|scheme| source.strip_prefix(scheme))
+        .unwrap_or(source);
+    let source = source.trim_end_matches('/');
+    source.strip_suffix(".git").unwrap_or(source)
+}
+
+/// checks that `labels` have values of `expected` ones; [SOURCE] is compared ignoring
+/// URL scheme, trailing `/` and `.git` suffix, other labels are compared exactly
+pub fn check(
+    labels: &BTreeMap<String, String>,
+    expected: &BTreeMap<String, String>,
+) -> eyre::Result<()> {
+    for (name, expected_value) in expected {
+        let Some(value) = labels.get(name) else {
+            return Err(eyre::eyre!(
+                "image has no `{}` label, expected `{}`",
+                name,
+                expected_value
+            ));
+        };
+        let matches = match name.as_str() {
+            SOURCE => normalize_source(value) == normalize_source(expected_value),
+            _ => value == expected_value,
+        };
+        if !matches {
+            return Err(eyre::eyre!(
+                "image label `{}` is `{}`, expected `{}`",
+                name,
+                value,
+                expected_value
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// [check] of labels of `inspected` image against `expected` ones,
+/// e.g. [WhitelistEntry::expected_labels](crate::types::whitelist::WhitelistEntry::expected_labels),
+/// reporting [SOURCE] and [REVISION]; returns all labels of the image
+pub fn verify(
+    inspected: &InspectedImage,
+    expected: &BTreeMap<String, String>,
+    reporter: &dyn Reporter,
+) -> eyre::Result<BTreeMap<String, String>> {
+    let labels = inspected.labels();
+    report_and_check(&labels, expected, reporter)?;
+    Ok(labels)
+}
+
+/// [verify] of local `docker_image` on a given [Backend]; the labels are also recorded
+/// into `labels` span of [spans], so that they're part of verification output of library callers
+pub fn verify_on(
+    docker_image: &str,
+    expected: &BTreeMap<String, String>,
+    backend: &Backend,
+    reporter: &dyn Reporter,
+) -> eyre::Result<BTreeMap<String, String>> {
+    let span = spans::labels(docker_image);
+    let _guard = span.enter();
+    let started = std::time::Instant::now();
+
+    let labels = labels_on(docker_image, backend)?;
+    span.record("labels", serde_json::to_string(&labels)?.as_str());
+    if let Some(source) = labels.get(SOURCE) {
+        span.record("source", source.as_str());
+    }
+    if let Some(revision) = labels.get(REVISION) {
+        span.record("revision", revision.as_str());
+    }
+    spans::record_duration(&span, started);
+    report_and_check(&labels, expected, reporter)?;
+    Ok(labels)
+}
+
+fn labels_on(docker_image: &str, backend: &Backend) -> eyre::Result<BTreeMap<String, String>> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        let inspect = client.inspect_image(docker_image)?;
+        let labels: Option<BTreeMap<String, String>> =
+            serde_json::from_value(inspect["Config"]["Labels"].clone())?;
+        return Ok(labels.unwrap_or_default());
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+    Ok(super::inspect::inspect(docker_image)?.labels())
+}
+
+fn report_and_check(
+    labels: &BTreeMap<String, String>,
+    expected: &BTreeMap<String, String>,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    let label = |name: &str| {
+        labels
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| "<none>".to_string())
+    };
+    reporter.report(
+        Message::new()
+            .title("image source:")
+            .normal(label(SOURCE))
+            .title("revision:")
+            .normal(label(REVISION)),
+    );
+    check(labels, expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{REVISION, SOURCE};
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let image = labels(&[
+            (SOURCE, "https://github.com/near/cargo-near.git"),
+            (REVISION, "0123456789abcdef"),
+        ]);
+
+        assert!(super::check(&image, &labels(&[])).is_ok());
+        assert!(super::check(&image, &labels(&[(SOURCE, "github.com/near/cargo-near")])).is_ok());
+        assert!(
+            super::check(
+                &image,
+                &labels(&[
+                    (SOURCE, "https://github.com/near/cargo-near/"),
+                    (REVISION, "0123456789abcdef")
+                ])
+            )
+            .is_ok()
+        );
+
+        let err =
+            super::check(&image, &labels(&[(SOURCE, "github.com/fork/cargo-near")])).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected `github.com/fork/cargo-near`")
+        );
+        assert!(super::check(&image, &labels(&[(REVISION, "0123456")])).is_err());
+        assert!(
+            super::check(
+                &labels(&[]),
+                &labels(&[(SOURCE, "github.com/near/cargo-near")])
+            )
+            .is_err()
+        );
+    }
+}
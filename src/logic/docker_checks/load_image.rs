@@ -0,0 +1,486 @@
+//! Loading of a build environment from an image archive instead of a registry, for offline
+//! verification.
+//!
+//! The archive is an [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md),
+//! either a directory or a tarball of it, which is also what `docker save` of docker 25+ produces.
+//! Before `docker load`, the archive is checked to contain the manifest with the digest pinned
+//! in `build_environment` and nothing else, and after it the loaded image is checked to be
+//! the pinned one. [build_opts] of the loaded image are then to be passed to
+//! [crate::logic::nep330_build::run_opts], so that the build needs no access to registry.
+//!
+//! [crate::logic::nep330_build::run] cannot use a loaded image: docker's classic image store
+//! records no repo digests for loaded images, so `build_environment`, pinned with a digest,
+//! isn't found locally and would be pulled from registry.
+use std::io::Write;
+use std::path::Path;
+
+use super::inspect::{InspectedImage, Platform};
+use crate::logic::internal::oci_layout::{
+    INDEX, INDEX_MEDIA_TYPES, Index, Layout, MANIFEST_MEDIA_TYPES, Manifest,
+};
+use crate::logic::spans;
+use crate::reporter::{self, Message, Reporter};
+use crate::types::image_reference::ImageReference;
+
+const OCI_LAYOUT: &str = "oci-layout";
+/// `manifest.json` of `docker save` archives, which predate OCI image layout
+const LEGACY_MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Clone)]
+pub struct Opts {
+    /// platforms the loaded image may be built for, `linux/amd64` by default
+    pub allowed_platforms: Vec<Platform>,
+    /// platform to load from a multi-platform archive, passed to `docker load --platform`
+    pub platform: Option<Platform>,
+}
+
+impl Default for Opts {
+    fn default() -> Self {
+        Self {
+            allowed_platforms: vec![Platform::linux_amd64()],
+            platform: None,
+        }
+    }
+}
+
+/// checks that `index.json` of the layout references nothing but the manifest with digest
+/// of `pinned`, directly or through its parent image index, and that its blob has this digest;
+/// returns digests the loaded image may be identified by: the pinned one, the parent index
+/// and configs of pinned manifests, which are image ids of docker's classic image store
+fn verify_manifest_digest(layout: &Layout, pinned: &ImageReference) -> eyre::Result<Vec<String>> {
+    let pinned_digest = pinned
+        .digest
+        .as_deref()
+        .ok_or_else(|| eyre::eyre!("`{}` isn't pinned with a digest", pinned))?;
+    let index = match layout.index()? {
+        Some(index) => index,
+        None if layout.read(LEGACY_MANIFEST)?.is_some() => {
+            return Err(eyre::eyre!(
+                "image archive has no `{}` of OCI image layout, so its manifest digest cannot be \
+                checked; it's probably produced by `docker save` of docker older than 25",
+                INDEX
+            ));
+        }
+        None => {
+            return Err(eyre::eyre!(
+                "image archive has neither `{}` nor `{}`",
+                INDEX,
+                OCI_LAYOUT
+            ));
+        }
+    };
+
+    let referenced = |index: &Index| {
+        index
+            .manifests
+            .iter()
+            .any(|descriptor| descriptor.digest == pinned_digest)
+    };
+    let mut identities = vec![pinned_digest.to_string()];
+    let mut unrelated = vec![];
+    for descriptor in &index.manifests {
+        if descriptor.digest == pinned_digest {
+            continue;
+        }
+        let is_parent = INDEX_MEDIA_TYPES.contains(&descriptor.media_type.as_str())
+            && referenced(&serde_json::from_slice(
+                &layout.read_blob(&descriptor.digest)?,
+            )?);
+        match is_parent {
+            true => identities.push(descriptor.digest.clone()),
+            false => unrelated.push(descriptor.digest.as_str()),
+        }
+    }
+    if identities.len() == 1 && !referenced(&index) {
+        return Err(eyre::eyre!(
+            "image archive doesn't contain manifest `{}` of `{}`, its index references: {}",
+            pinned_digest,
+            pinned.familiar_name(),
+            index
+                .manifests
+                .iter()
+                .map(|descriptor| descriptor.digest.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    // `docker load` loads every image of the archive
+    if !unrelated.is_empty() {
+        return Err(eyre::eyre!(
+            "image archive references `{}` besides manifest `{}` of `{}`, \
+            only the pinned image may be in it",
+            unrelated.join("`, `"),
+            pinned_digest,
+            pinned.familiar_name()
+        ));
+    }
+
+    let blob = layout.read_blob(pinned_digest)?;
+    let manifests = match serde_json::from_slice::<Manifest>(&blob) {
+        Ok(manifest) => vec![manifest],
+        // pinned multi-platform index, only manifests of some platforms may be in the archive
+        Err(_) => serde_json::from_slice::<Index>(&blob)?
+            .manifests
+            .iter()
+            .filter(|descriptor| MANIFEST_MEDIA_TYPES.contains(&descriptor.media_type.as_str()))
+            .filter_map(|descriptor| layout.read_blob(&descriptor.digest).ok())
+            .filter_map(|blob| serde_json::from_slice::<Manifest>(&blob).ok())
+            .collect(),
+    };
+    identities.extend(
+        manifests
+            .into_iter()
+            .filter_map(|manifest| manifest.config)
+            .map(|config| config.digest),
+    );
+    Ok(identities)
+}
+
+/// whether `inspected` image is the pinned one, by its id being one of `identities`
+/// of [verify_manifest_digest] or by its repo digest
+fn is_pinned_image(inspected: &InspectedImage, pinned_digest: &str, identities: &[String]) -> bool {
+    identities.contains(&inspected.id)
+        || inspected.repo_digests.iter().any(|repo_digest| {
+            repo_digest
+                .rsplit_once('@')
+                .is_some_and(|(_, digest)| digest == pinned_digest)
+        })
+}
+
+pub fn check(archive: &Path, docker_image: &str, quiet: bool) -> eyre::Result<InspectedImage> {
+    check_opts(
+        archive,
+        docker_image,
+        &Opts::default(),
+        reporter::from_quiet(quiet).as_ref(),
+    )
+}
+
+/// checks that `archive` contains only the manifest of `docker_image`, pinned with a digest,
+/// loads it with `docker load` and checks that the loaded image is the pinned one
+/// and its platform
+pub fn check_opts(
+    archive: &Path,
+    docker_image: &str,
+    opts: &Opts,
+    reporter: &dyn Reporter,
+) -> eyre::Result<InspectedImage> {
+    let span = spans::load(archive, docker_image);
+    let _guard = span.enter();
+    let started = std::time::Instant::now();
+
+    reporter.report(
+        Message::new()
+            .title("docker image to be used:")
+            .normal(docker_image)
+            .title("loaded from:")
+            .normal(archive.display().to_string()),
+    );
+    reporter.separator();
+
+    let pinned: ImageReference = docker_image.parse()?;
+    let layout = Layout::open(archive)?;
+    let identities = verify_manifest_digest(&layout, &pinned).map_err(|err| {
+        err.wrap_err(format!(
+            "Image archive `{}` doesn't match `{}`",
+            archive.display(),
+            docker_image
+        ))
+    })?;
+
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.arg("load");
+    let platform = opts.platform.as_ref().map(ToString::to_string);
+    if let Some(ref platform) = platform {
+        docker_cmd.args(["--platform", platform]);
+    }
+    let output = match layout {
+        Layout::Tar { path, .. } => {
+            docker_cmd.arg("--input").arg(path);
+            docker_cmd.output()
+        }
+        // `docker load` only accepts tarballs, so the layout directory is streamed as one
+        Layout::Dir(dir) => load_dir(&mut docker_cmd, dir),
+    };
+    let err_report = format!("Image archive `{}` could not be loaded!", archive.display());
+    let output = super::handle_io_error(
+        &docker_cmd,
+        output,
+        eyre::eyre!(err_report.clone()),
+        reporter,
+    )?;
+    spans::record_exit_code(&span, &output.status);
+    spans::record_duration(&span, started);
+    if !output.status.success() {
+        reporter.report(Message::new().warning(String::from_utf8_lossy(&output.stderr)));
+        super::print::command_status(output.status, docker_cmd, reporter);
+        return Err(eyre::eyre!(err_report));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let loaded = loaded_images(&stdout);
+    if loaded.is_empty() {
+        return Err(eyre::eyre!(
+            "no loaded image in output of `{:?}`: {}",
+            docker_cmd,
+            stdout.trim()
+        ));
+    }
+    let pinned_digest = pinned.digest.as_deref().unwrap_or_default();
+    let mut inspected = None;
+    for image in &loaded {
+        let candidate = super::inspect::inspect(image)?;
+        if is_pinned_image(&candidate, pinned_digest, &identities) {
+            inspected = Some(candidate);
+            break;
+        }
+    }
+    let inspected = inspected.ok_or_else(|| {
+        eyre::eyre!(
+            "none of loaded images {:?} is pinned `{}@{}`",
+            loaded,
+            pinned.familiar_name(),
+            pinned_digest
+        )
+    })?;
+    inspected.verify_platform(&pinned.familiar_name(), &opts.allowed_platforms)?;
+    reporter.report(
+        Message::new()
+            .title("local image id:")
+            .normal(inspected.id.as_str())
+            .title("platform:")
+            .normal(inspected.platform().to_string()),
+    );
+    span.record("image_id", inspected.id.as_str());
+    Ok(inspected)
+}
+
+/// [crate::logic::nep330_build::Opts] to build in `loaded` image, as returned by [check_opts]:
+/// containers are run from its [InspectedImage::id] for its platform, and nothing is pulled
+pub fn build_opts(loaded: &InspectedImage) -> crate::logic::nep330_build::Opts {
+    crate::logic::nep330_build::Opts {
+        image_id: Some(loaded.id.clone()),
+        platform: Some(loaded.platform()),
+        pull_policy: super::pull_image::PullPolicy::Never,
+        ..Default::default()
+    }
+}
+
+fn load_dir(
+    docker_cmd: &mut std::process::Command,
+    dir: &Path,
+) -> std::io::Result<std::process::Output> {
+    docker_cmd
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let mut child = docker_cmd.spawn()?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let written = (|| {
+        let mut builder = tar::Builder::new(stdin);
+        builder.append_dir_all(".", dir)?;
+        builder.into_inner()?.flush()
+    })();
+    let output = child.wait_with_output()?;
+    // a failure of `docker load` itself is more informative than a broken pipe
+    if output.status.success() {
+        written?;
+    }
+    Ok(output)
+}
+
+/// `Loaded image: <reference>` or `Loaded image ID: sha256:<hex>` lines of `docker load` output
+fn loaded_images(stdout: &str) -> Vec<&str> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("Loaded image ID: ")
+                .or_else(|| line.strip_prefix("Loaded image: "))
+                .map(str::trim)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::docker_checks::inspect::InspectedImage;
+    use crate::logic::docker_checks::pull_image::PullPolicy;
+    use crate::logic::internal::oci_layout::{Layout, sha256_digest as sha256};
+
+    const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+    const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+    /// layout of `docker save`: `index.json` → image index → manifest
+    fn write_layout(dir: &std::path::Path) -> eyre::Result<(String, String)> {
+        let blobs = dir.join("blobs/sha256");
+        std::fs::create_dir_all(&blobs)?;
+        let manifest = br#"{"schemaVersion":2,"layers":[]}"#.to_vec();
+        let manifest_digest = sha256(&manifest);
+        let nested = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "manifests": [{"mediaType": MANIFEST_MEDIA_TYPE, "digest": manifest_digest, "size": manifest.len()}],
+        }))?;
+        let nested_digest = sha256(&nested);
+        for (digest, contents) in [(&manifest_digest, &manifest), (&nested_digest, &nested)] {
+            std::fs::write(
+                blobs.join(digest.strip_prefix("sha256:").expect("sha256")),
+                contents,
+            )?;
+        }
+        std::fs::write(
+            dir.join("index.json"),
+            serde_json::to_vec(&serde_json::json!({
+                "schemaVersion": 2,
+                "manifests": [{"mediaType": INDEX_MEDIA_TYPE, "digest": nested_digest, "size": nested.len()}],
+            }))?,
+        )?;
+        std::fs::write(dir.join("oci-layout"), r#"{"imageLayoutVersion":"1.0.0"}"#)?;
+        Ok((nested_digest, manifest_digest))
+    }
+
+    #[test]
+    fn test_verify_manifest_digest() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let layout_dir = tempdir.path().join("layout");
+        let (index_digest, manifest_digest) = write_layout(&layout_dir)?;
+
+        let tarball = tempdir.path().join("image.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&tarball)?);
+        builder.append_dir_all(".", &layout_dir)?;
+        builder.finish()?;
+
+        for layout in [Layout::Dir(&layout_dir), Layout::open(&tarball)?] {
+            for digest in [&index_digest, &manifest_digest] {
+                let pinned = format!("sourcescan/cargo-near:tag@{}", digest).parse()?;
+                super::verify_manifest_digest(&layout, &pinned)?;
+            }
+            let other = format!("sourcescan/cargo-near:tag@sha256:{}", "0".repeat(64)).parse()?;
+            let err = super::verify_manifest_digest(&layout, &other).unwrap_err();
+            assert!(err.to_string().contains("doesn't contain manifest"));
+        }
+
+        // tampered blob
+        let manifest_path = layout_dir.join("blobs/sha256").join(
+            manifest_digest
+                .strip_prefix("sha256:")
+                .expect("sha256 digest"),
+        );
+        std::fs::write(&manifest_path, b"{}")?;
+        let pinned = format!("sourcescan/cargo-near:tag@{}", manifest_digest).parse()?;
+        let err = super::verify_manifest_digest(&Layout::Dir(&layout_dir), &pinned).unwrap_err();
+        assert!(err.to_string().contains("has digest"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_docker_save() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        std::fs::write(tempdir.path().join("manifest.json"), "[]")?;
+        let pinned = format!("sourcescan/cargo-near:tag@sha256:{}", "0".repeat(64)).parse()?;
+        let err = super::verify_manifest_digest(&Layout::Dir(tempdir.path()), &pinned).unwrap_err();
+        assert!(err.to_string().contains("docker older than 25"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_two_images() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let layout_dir = tempdir.path().join("layout");
+        let (index_digest, manifest_digest) = write_layout(&layout_dir)?;
+
+        // another image, which `docker load` would load along with the pinned one
+        let other = br#"{"schemaVersion":2,"config":{"mediaType":"application/vnd.oci.image.config.v1+json","digest":"sha256:0000000000000000000000000000000000000000000000000000000000000000","size":2},"layers":[]}"#.to_vec();
+        let other_digest = sha256(&other);
+        std::fs::write(
+            layout_dir
+                .join("blobs/sha256")
+                .join(other_digest.strip_prefix("sha256:").expect("sha256")),
+            &other,
+        )?;
+        let index_path = layout_dir.join("index.json");
+        let mut index: serde_json::Value = serde_json::from_slice(&std::fs::read(&index_path)?)?;
+        index["manifests"]
+            .as_array_mut()
+            .expect("manifests")
+            .push(serde_json::json!({"mediaType": MANIFEST_MEDIA_TYPE, "digest": other_digest, "size": other.len()}));
+        std::fs::write(&index_path, serde_json::to_vec(&index)?)?;
+
+        for digest in [&index_digest, &manifest_digest] {
+            let pinned = format!("sourcescan/cargo-near:tag@{}", digest).parse()?;
+            let err =
+                super::verify_manifest_digest(&Layout::Dir(&layout_dir), &pinned).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains(&format!("references `{}`", other_digest))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_pinned_image() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let (index_digest, manifest_digest) = write_layout(tempdir.path())?;
+        let pinned = format!("sourcescan/cargo-near:tag@{}", manifest_digest).parse()?;
+        let identities = super::verify_manifest_digest(&Layout::Dir(tempdir.path()), &pinned)?;
+        assert!(identities.contains(&index_digest));
+
+        let inspected = |id: &str, repo_digests: &[String]| -> eyre::Result<InspectedImage> {
+            Ok(serde_json::from_value(serde_json::json!({
+                "Id": id,
+                "RepoDigests": repo_digests,
+                "Os": "linux",
+                "Architecture": "amd64",
+            }))?)
+        };
+        let other = format!("sha256:{}", "1".repeat(64));
+        // containerd image store
+        assert!(super::is_pinned_image(
+            &inspected(&index_digest, &[])?,
+            &manifest_digest,
+            &identities
+        ));
+        assert!(super::is_pinned_image(
+            &inspected(
+                &other,
+                &[format!("sourcescan/cargo-near@{}", manifest_digest)]
+            )?,
+            &manifest_digest,
+            &identities
+        ));
+        assert!(!super::is_pinned_image(
+            &inspected(&other, &[format!("sourcescan/cargo-near@{}", other)])?,
+            &manifest_digest,
+            &identities
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_opts() -> eyre::Result<()> {
+        let loaded: InspectedImage = serde_json::from_value(serde_json::json!({
+            "Id": format!("sha256:{}", "1".repeat(64)),
+            "Os": "linux",
+            "Architecture": "amd64",
+        }))?;
+        let opts = super::build_opts(&loaded);
+        assert_eq!(opts.image_id.as_deref(), Some(loaded.id.as_str()));
+        assert_eq!(
+            opts.platform,
+            Some(crate::logic::platform::Platform::linux_amd64())
+        );
+        assert_eq!(opts.pull_policy, PullPolicy::Never);
+        Ok(())
+    }
+
+    #[test]
+    fn test_loaded_images() {
+        assert_eq!(
+            super::loaded_images(
+                "Loaded image: sourcescan/cargo-near:0.13.4-rust-1.85.0\nLoaded image ID: sha256:abc\n"
+            ),
+            vec!["sourcescan/cargo-near:0.13.4-rust-1.85.0", "sha256:abc"]
+        );
+        assert!(super::loaded_images("").is_empty());
+    }
+}
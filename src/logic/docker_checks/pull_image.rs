@@ -1,42 +1,189 @@
-use std::process::Stdio;
+use super::inspect::{InspectedImage, Platform};
+use crate::logic::mirror::MirrorRule;
+use crate::logic::spans;
+use crate::reporter::{self, Message, Reporter};
 
-use colored::Colorize;
+/// Whether an image is pulled from registry, see [check_opts]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PullPolicy {
+    /// image is always pulled, by [check_opts] and so by [crate::logic::nep330_build::run_opts],
+    /// unless the latter is given [image_id](crate::logic::nep330_build::Opts::image_id)
+    #[default]
+    Always,
+    /// image is pulled only if it's absent in local image store, looked up by its digest
+    IfMissing,
+    /// image is never pulled, failing if it's absent in local image store,
+    /// e.g. for air-gapped environments, where it's been `docker load`-ed beforehand
+    Never,
+}
+
+impl PullPolicy {
+    /// value of `docker run --pull`; [check_opts] has already pulled the image per policy,
+    /// either on its own or within [crate::logic::nep330_build::run_opts], so `docker run` itself
+    /// pulls at most a missing image
+    pub fn run_arg(&self) -> &'static str {
+        match self {
+            Self::Always | Self::IfMissing => "missing",
+            Self::Never => "never",
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::IfMissing => "if-missing",
+            Self::Never => "never",
+        }
+    }
+}
 
-use crate::pretty_print::quiet_println;
+#[derive(Debug, Clone)]
+pub struct Opts {
+    /// platforms the pulled image may be built for, `linux/amd64` by default
+    pub allowed_platforms: Vec<Platform>,
+    /// platform to pull, passed to `docker image pull --platform`, e.g.
+    /// [WhitelistEntry::platform](crate::types::whitelist::WhitelistEntry::platform);
+    /// it has to be one of [Self::allowed_platforms], and the pulled image has to be built for it
+    pub platform: Option<Platform>,
+    pub pull_policy: PullPolicy,
+    /// rules, by which the image is pulled through a registry mirror with the same digest
+    pub mirrors: Vec<MirrorRule>,
+}
+
+impl Default for Opts {
+    fn default() -> Self {
+        Self {
+            allowed_platforms: vec![Platform::linux_amd64()],
+            platform: None,
+            pull_policy: PullPolicy::default(),
+            mirrors: vec![],
+        }
+    }
+}
 
 pub fn check(docker_image: &str, quiet: bool) -> eyre::Result<()> {
-    quiet_println!(
-        quiet,
-        "{} {}",
-        "docker image to be used:".green(),
-        docker_image
+    check_with_reporter(docker_image, reporter::from_quiet(quiet).as_ref())
+}
+
+pub fn check_with_reporter(docker_image: &str, reporter: &dyn Reporter) -> eyre::Result<()> {
+    check_opts(docker_image, &Opts::default(), reporter)?;
+    Ok(())
+}
+
+/// pulls `docker_image`, rewritten by [Opts::mirrors], according to [Opts::pull_policy]
+/// and verifies the local image with [super::inspect::verify];
+/// [InspectedImage::id] can be passed to [crate::logic::nep330_build::Opts::image_id],
+/// so that the build runs in the very same image
+pub fn check_opts(
+    docker_image: &str,
+    opts: &Opts,
+    reporter: &dyn Reporter,
+) -> eyre::Result<InspectedImage> {
+    let span = spans::pull(docker_image);
+    let _guard = span.enter();
+    let started = std::time::Instant::now();
+    span.record("pull_policy", opts.pull_policy.as_str());
+    let allowed_platforms = match opts.platform {
+        Some(ref platform) => {
+            if !opts
+                .allowed_platforms
+                .iter()
+                .any(|allowed| platform.is_allowed_by(allowed))
+            {
+                return Err(eyre::eyre!(
+                    "platform `{}` to pull isn't one of allowed platforms",
+                    platform
+                ));
+            }
+            span.record("platform", platform.to_string());
+            std::slice::from_ref(platform)
+        }
+        None => opts.allowed_platforms.as_slice(),
+    };
+
+    reporter.report(
+        Message::new()
+            .title("docker image to be used:")
+            .normal(docker_image),
     );
-    quiet_println!(quiet,);
+    let mirrored = crate::logic::mirror::resolve(docker_image, &opts.mirrors, &span, reporter)?;
+    let docker_image = mirrored.as_str();
+    reporter.separator();
 
-    let mut docker_cmd = docker_pull_cmd(docker_image);
+    let pull = match opts.pull_policy {
+        PullPolicy::Always => true,
+        PullPolicy::IfMissing => !is_present(docker_image, allowed_platforms),
+        PullPolicy::Never => {
+            if let Err(err) = super::inspect::inspect(docker_image) {
+                tracing::debug!("{:?}", err);
+                return Err(eyre::eyre!(
+                    "Image `{}` is absent in local image store, and pull policy is `never`; \
+                    consider `docker load`-ing it",
+                    docker_image
+                ));
+            }
+            false
+        }
+    };
+    if pull {
+        pull_image(docker_image, opts.platform.as_ref(), &span, reporter)?;
+    } else {
+        reporter.report(
+            Message::new()
+                .title("image is present locally, pull skipped by policy:")
+                .normal(opts.pull_policy.as_str()),
+        );
+    }
+    spans::record_duration(&span, started);
+    let inspected = super::inspect::verify(docker_image, allowed_platforms, reporter)?;
+    span.record("image_id", inspected.id.as_str());
+    Ok(inspected)
+}
 
-    let err_report = format!("Image `{}` could not be found in registry!", docker_image);
-    if quiet {
-        docker_cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+/// local image of `docker_image`, pinned with a digest, passes [InspectedImage::verify]
+fn is_present(docker_image: &str, allowed_platforms: &[Platform]) -> bool {
+    let result = docker_image.parse().and_then(|pinned| {
+        super::inspect::inspect(docker_image)?.verify(&pinned, allowed_platforms)
+    });
+    if let Err(ref err) = result {
+        tracing::debug!("image `{}` is to be pulled: {:?}", docker_image, err);
     }
+    result.is_ok()
+}
+
+fn pull_image(
+    docker_image: &str,
+    platform: Option<&Platform>,
+    span: &tracing::Span,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    let mut docker_cmd = docker_pull_cmd(docker_image, platform);
+
+    let err_report = format!("Image `{}` could not be found in registry!", docker_image);
+    reporter::configure_child(reporter, &mut docker_cmd);
     let status_result = docker_cmd.status();
     let status = super::handle_io_error(
         &docker_cmd,
         status_result,
         eyre::eyre!(err_report.clone()),
-        quiet,
+        reporter,
     )?;
+    spans::record_exit_code(span, &status);
     if !status.success() {
-        super::print::command_status(status, docker_cmd, quiet);
+        super::print::command_status(status, docker_cmd, reporter);
         return Err(eyre::eyre!(err_report));
     }
     Ok(())
 }
 
-fn docker_pull_cmd(image: &str) -> std::process::Command {
+fn docker_pull_cmd(image: &str, platform: Option<&Platform>) -> std::process::Command {
+    let platform = platform.map(ToString::to_string);
     let docker_cmd: std::process::Command = {
         let docker_args = {
             let mut docker_args = vec!["pull"];
+            if let Some(ref platform) = platform {
+                docker_args.extend(["--platform", platform]);
+            }
             docker_args.push(image);
             docker_args
         };
@@ -48,3 +195,23 @@ fn docker_pull_cmd(image: &str) -> std::process::Command {
     };
     docker_cmd
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Opts, PullPolicy};
+
+    #[test]
+    fn test_never_pull_absent_image() {
+        let opts = Opts {
+            pull_policy: PullPolicy::Never,
+            ..Default::default()
+        };
+        let err = super::check_opts(
+            "near-verify-rs/absent:tag@sha256:0000000000000000000000000000000000000000000000000000000000000000",
+            &opts,
+            &crate::reporter::Silent,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("pull policy is `never`"));
+    }
+}
@@ -1,37 +1,29 @@
-use colored::Colorize;
-
-use crate::pretty_print::quiet_println;
-
-const ERR_SANITY: &str = "`docker` sanity check failed!";
-
-const PERM_DENIED_STATUS: i32 = 126;
+use super::environment::DockerEnvironment;
+use super::pull_image::PullPolicy;
+use crate::logic::nep330_build::Backend;
+use crate::reporter::{self, Reporter};
 
 pub fn check(quiet: bool) -> eyre::Result<()> {
-    let mut docker_cmd = std::process::Command::new("docker");
-    docker_cmd.args(["run", "--rm", "hello-world"]);
-    let output_result = docker_cmd.output();
-    let output =
-        super::handle_io_error(&docker_cmd, output_result, eyre::eyre!(ERR_SANITY), quiet)?;
+    check_with_reporter(reporter::from_quiet(quiet).as_ref())
+}
 
-    if !output.status.success() {
-        let stderr = std::str::from_utf8(&output.stderr)?;
-        quiet_println!(quiet,);
-        quiet_println!(quiet, "{}", stderr.yellow());
-        if permission_denied(&output.status, stderr)? {
-            quiet_println!(quiet, "{}", "Permission denied!".cyan());
-            super::print::installation_links(quiet);
-            super::print::linux_postinstall_steps(quiet);
-        } else {
-            super::print::installation_links(quiet);
-        }
-        super::print::command_status(output.status, docker_cmd, quiet);
-        return Err(eyre::eyre!(ERR_SANITY));
-    }
+pub fn check_with_reporter(reporter: &dyn Reporter) -> eyre::Result<()> {
+    check_on(&Backend::Cli, reporter)?;
     Ok(())
 }
 
-fn permission_denied(status: &std::process::ExitStatus, stderr: &str) -> eyre::Result<bool> {
-    let exit_code_match = status.code().unwrap_or(-1) == PERM_DENIED_STATUS;
-    let stderr_match = stderr.to_lowercase().contains("permission denied");
-    Ok(exit_code_match || stderr_match)
+/// `pull_policy` used to apply to `hello-world` image, which the check doesn't run anymore
+#[deprecated(
+    note = "sanity check no longer pulls any image, use `check_with_reporter` or `check_on` instead"
+)]
+pub fn check_with_pull_policy(
+    _pull_policy: PullPolicy,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    check_with_reporter(reporter)
+}
+
+/// [super::environment::diagnose] of docker daemon of `backend`
+pub fn check_on(backend: &Backend, reporter: &dyn Reporter) -> eyre::Result<DockerEnvironment> {
+    super::environment::diagnose(backend, reporter)
 }
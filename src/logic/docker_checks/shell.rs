@@ -0,0 +1,206 @@
+use super::environment::Problem;
+use super::pull_image::PullPolicy;
+use crate::logic::container_cleanup;
+use crate::logic::nep330_build::Backend;
+use crate::logic::platform::Platform;
+use crate::reporter::{Message, Reporter};
+
+pub const SHELL: &str = "/bin/bash";
+const PROBE_SCRIPT: &str = "exit 0";
+
+/// `script` run by [SHELL], which is appended to `docker run <image>`, keeping entrypoint of the image;
+/// [crate::logic::nep330_build::ExecutionForm::Shell] builds and the probe of [check] are run alike
+pub(crate) fn command(script: &str) -> Vec<&str> {
+    vec![SHELL, "-c", script]
+}
+
+/// exit code of a container, which couldn't execute its command: 126 if it isn't executable,
+/// 127 if it's not found
+fn is_missing_shell(exit_code: Option<i64>, error: &str) -> bool {
+    matches!(exit_code, Some(126) | Some(127)) || error.contains("executable file not found")
+}
+
+fn err_missing_shell(docker_image: &str) -> eyre::Report {
+    eyre::eyre!(
+        "Image `{}` has no usable `{}`, consider running build command in exec form",
+        docker_image,
+        SHELL
+    )
+}
+
+/// Checks that `docker_image` has a usable [SHELL], which is required to run
+/// a build command in [crate::logic::nep330_build::ExecutionForm::Shell]
+pub fn check(docker_image: &str, reporter: &dyn Reporter) -> eyre::Result<()> {
+    check_for(docker_image, None, PullPolicy::default(), reporter)
+}
+
+/// [check] of `docker_image` variant for `platform`, pulled according to [PullPolicy::run_arg];
+/// only a probe container, which has started and couldn't execute [SHELL], is reported
+/// as a missing shell, failures to run it at all are passed through
+pub fn check_for(
+    docker_image: &str,
+    platform: Option<&Platform>,
+    pull_policy: PullPolicy,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    let name = probe_name();
+    let container_guard = container_cleanup::Guard::new(name.clone(), Backend::Cli);
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.args([
+        "run",
+        "--rm",
+        "--name",
+        &name,
+        "--pull",
+        pull_policy.run_arg(),
+    ]);
+    for (label, value) in container_cleanup::labels(docker_image) {
+        docker_cmd.args(["--label", &format!("{}={}", label, value)]);
+    }
+    if let Some(platform) = platform {
+        docker_cmd.args(["--platform", &platform.to_string()]);
+    }
+    docker_cmd.arg(docker_image).args(command(PROBE_SCRIPT));
+    let output_result = docker_cmd.output();
+    let output = super::handle_io_error(
+        &docker_cmd,
+        output_result,
+        eyre::eyre!("cannot execute `docker` to probe image `{}`", docker_image),
+        reporter,
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        reporter.separator();
+        reporter.report(Message::new().warning(stderr.trim()));
+        let missing_shell = is_missing_shell(output.status.code().map(i64::from), &stderr);
+        if !missing_shell {
+            Problem::classify(&stderr).report_hints(reporter);
+        }
+        super::print::command_status(output.status, docker_cmd, reporter);
+        if missing_shell {
+            return Err(err_missing_shell(docker_image));
+        }
+        return Err(eyre::eyre!(
+            "cannot run shell probe of image `{}`: {}",
+            docker_image,
+            stderr.trim()
+        ));
+    }
+    // `--rm` is only known to have removed the container of a successful probe
+    container_guard.removed();
+    Ok(())
+}
+
+fn probe_name() -> String {
+    format!("near-verify-rs-shell-{}", crate::logic::unique_suffix())
+}
+
+/// [check_for] on a given [Backend]; docker engine API never pulls
+pub fn check_on(
+    docker_image: &str,
+    platform: Option<&Platform>,
+    pull_policy: PullPolicy,
+    backend: &Backend,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        let name = probe_name();
+        let container_guard = container_cleanup::Guard::new(name.clone(), backend.clone());
+        let body = serde_json::json!({
+            "Image": docker_image,
+            "Labels": container_cleanup::labels(docker_image)
+                .into_iter()
+                .collect::<std::collections::BTreeMap<_, _>>(),
+            "Cmd": command(PROBE_SCRIPT),
+        });
+        let result = client.run_to_completion(
+            &name,
+            platform.map(ToString::to_string).as_deref(),
+            &body,
+            |_, _| {},
+        );
+        // container is removed by [Client::run_to_completion] either way
+        container_guard.removed();
+        let exit_code = match result {
+            Ok((exit_code, _)) => exit_code,
+            Err(err) => {
+                let error = format!("{:#}", err);
+                if is_missing_shell(None, &error) {
+                    return Err(err.wrap_err(err_missing_shell(docker_image)));
+                }
+                reporter.separator();
+                reporter.report(Message::new().warning(error.as_str()));
+                Problem::classify(&error).report_hints(reporter);
+                return Err(err.wrap_err(format!(
+                    "cannot run shell probe of image `{}`",
+                    docker_image
+                )));
+            }
+        };
+        if exit_code != 0 {
+            reporter.separator();
+            reporter.report(
+                Message::new()
+                    .failure("Shell probe exited with code")
+                    .normal(exit_code.to_string()),
+            );
+            if is_missing_shell(Some(exit_code), "") {
+                return Err(err_missing_shell(docker_image));
+            }
+            return Err(eyre::eyre!(
+                "shell probe of image `{}` exited with code {}",
+                docker_image,
+                exit_code
+            ));
+        }
+        return Ok(());
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+    check_for(docker_image, platform, pull_policy, reporter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PullPolicy;
+    use crate::reporter::Silent;
+
+    #[test]
+    fn test_is_missing_shell() {
+        assert!(super::is_missing_shell(Some(127), ""));
+        assert!(super::is_missing_shell(Some(126), ""));
+        assert!(super::is_missing_shell(
+            None,
+            "OCI runtime create failed: exec: \"/bin/bash\": stat /bin/bash: executable file not found in $PATH"
+        ));
+        assert!(!super::is_missing_shell(
+            Some(125),
+            "Unable to find image locally"
+        ));
+        assert!(!super::is_missing_shell(
+            None,
+            "Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?"
+        ));
+    }
+
+    #[test]
+    fn test_check_failure() {
+        // fails whether `docker` is missing, its daemon is down or the image is absent,
+        // none of which is a missing shell
+        let image = "near-verify-rs-test/no-such-image:latest";
+        let err = super::check_for(image, None, PullPolicy::Never, &Silent).unwrap_err();
+        let err = err.to_string();
+        assert!(!err.contains("has no usable"), "{}", err);
+        let cause_found = [
+            "cannot execute `docker`",
+            "Cannot connect to the Docker daemon",
+            "Unable to find image",
+            "No such image",
+        ]
+        .iter()
+        .any(|cause| err.contains(cause));
+        assert!(cause_found, "{}", err);
+        assert!(err.contains(image), "{}", err);
+    }
+}
@@ -0,0 +1,459 @@
+//! [cosign](https://github.com/sigstore/cosign) signature verification of a build environment,
+//! proving who built the image pinned in `build_environment`, rather than only that it hasn't changed.
+//!
+//! Signatures of image with digest `sha256:<hex>` are stored in a manifest, tagged `sha256-<hex>.sig`
+//! in the same repository; each of its layers is a simple signing payload, naming the digest,
+//! with an ECDSA P-256 signature of it in [SIGNATURE_ANNOTATION].
+use p256::ecdsa::signature::Verifier;
+use p256::pkcs8::DecodePublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::logic::internal::oci_layout::{self, Layout, Manifest, REF_NAME_ANNOTATION};
+use crate::logic::internal::registry;
+use crate::logic::spans;
+use crate::reporter::{Message, Reporter};
+use crate::types::image_reference::ImageReference;
+
+pub const SIMPLE_SIGNING_MEDIA_TYPE: &str = "application/vnd.dev.cosign.simplesigning.v1+json";
+/// layer annotation with base64 encoded signature of the layer's payload
+pub const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+/// `kind` annotation of signatures manifest in layouts, written by `cosign save`
+const SIGNATURES_KIND: &str = "dev.cosignproject.cosign/sigs";
+
+/// named public key, e.g. `cosign.pub` of `cosign generate-key-pair`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKey {
+    /// name, referred to by [WhitelistEntry::required_signers](crate::types::whitelist::WhitelistEntry::required_signers)
+    pub name: String,
+    /// PEM encoded ECDSA P-256 public key
+    pub pem: String,
+}
+
+/// Where signatures are fetched from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Source {
+    /// repository of the image in its registry
+    #[default]
+    Registry,
+    /// OCI image layout directory or tarball, e.g. written by `cosign save`
+    OciLayout(std::path::PathBuf),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Opts {
+    pub keys: Vec<PublicKey>,
+    pub source: Source,
+}
+
+/// simple signing payload with its signature
+struct Signed {
+    payload: Vec<u8>,
+    signature: String,
+}
+
+impl Signed {
+    /// [None] if the payload names `digest` and its signature is valid for `key`,
+    /// the reason it isn't otherwise
+    fn invalid_reason(&self, digest: &str, key: &p256::ecdsa::VerifyingKey) -> Option<String> {
+        let payload: serde_json::Value = match serde_json::from_slice(&self.payload) {
+            Ok(payload) => payload,
+            Err(err) => return Some(format!("malformed payload: {}", err)),
+        };
+        let signed_digest = payload["critical"]["image"]["docker-manifest-digest"].as_str();
+        if signed_digest != Some(digest) {
+            return Some(format!("payload is for digest {:?}", signed_digest));
+        }
+        let signature = match base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            &self.signature,
+        ) {
+            Ok(signature) => signature,
+            Err(err) => return Some(format!("malformed base64 of signature: {}", err)),
+        };
+        let signature = match p256::ecdsa::Signature::from_der(&signature) {
+            Ok(signature) => signature,
+            Err(err) => return Some(format!("malformed signature: {}", err)),
+        };
+        key.verify(&self.payload, &signature)
+            .err()
+            .map(|err| format!("signature doesn't verify: {}", err))
+    }
+}
+
+/// `sha256-<hex>.sig` tag of signatures of image with `sha256:<hex>` digest
+pub fn signature_tag(digest: &str) -> eyre::Result<String> {
+    let hex = digest
+        .strip_prefix("sha256:")
+        .ok_or_else(|| eyre::eyre!("only sha256 digests are supported, got `{}`", digest))?;
+    Ok(format!("sha256-{}.sig", hex))
+}
+
+fn signed_layers(
+    manifest: &[u8],
+    mut read_blob: impl FnMut(&str) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<Vec<Signed>> {
+    let manifest: Manifest = serde_json::from_slice(manifest)?;
+    manifest
+        .layers
+        .iter()
+        .filter(|layer| layer.media_type == SIMPLE_SIGNING_MEDIA_TYPE)
+        .filter_map(|layer| {
+            let signature = layer.annotations.get(SIGNATURE_ANNOTATION)?.clone();
+            Some(read_blob(&layer.digest).map(|payload| Signed { payload, signature }))
+        })
+        .collect()
+}
+
+fn fetch(pinned: &ImageReference, digest: &str, source: &Source) -> eyre::Result<Vec<Signed>> {
+    let tag = signature_tag(digest)?;
+    match source {
+        Source::Registry => {
+            let client = registry::Client::new(pinned);
+            let manifest = client.manifest(&tag)?.ok_or_else(|| {
+                eyre::eyre!(
+                    "no signatures `{}` of `{}` in registry",
+                    tag,
+                    pinned.familiar_name()
+                )
+            })?;
+            signed_layers(&manifest, |digest| client.blob(digest))
+        }
+        Source::OciLayout(path) => {
+            let layout = Layout::open(path)?;
+            let index = layout.index()?.ok_or_else(|| {
+                eyre::eyre!(
+                    "`{}` has no `{}` of OCI image layout",
+                    path.display(),
+                    oci_layout::INDEX
+                )
+            })?;
+            let descriptor = index
+                .manifests
+                .iter()
+                .find(|descriptor| descriptor.annotations.get(REF_NAME_ANNOTATION) == Some(&tag))
+                .or_else(|| {
+                    index.manifests.iter().find(|descriptor| {
+                        descriptor.annotations.get("kind").map(String::as_str)
+                            == Some(SIGNATURES_KIND)
+                    })
+                })
+                .ok_or_else(|| eyre::eyre!("no signatures `{}` in `{}`", tag, path.display()))?;
+            let manifest = layout.read_blob(&descriptor.digest)?;
+            signed_layers(&manifest, |digest| layout.read_blob(digest))
+        }
+    }
+}
+
+/// verifies that `docker_image`, pinned with a digest, is signed by at least one of [Opts::keys],
+/// and by each of `required_signers` of them; returns names of the keys, which signed it
+pub fn verify(
+    docker_image: &str,
+    opts: &Opts,
+    required_signers: &[String],
+    reporter: &dyn Reporter,
+) -> eyre::Result<Vec<String>> {
+    let span = spans::signature(docker_image);
+    let _guard = span.enter();
+    let started = std::time::Instant::now();
+
+    let pinned: ImageReference = docker_image.parse()?;
+    let digest = pinned
+        .digest
+        .as_deref()
+        .ok_or_else(|| eyre::eyre!("`{}` isn't pinned with a digest", docker_image))?;
+    if opts.keys.is_empty() {
+        return Err(eyre::eyre!(
+            "no public keys are configured to verify signatures with"
+        ));
+    }
+    if let Some(unknown) = required_signers
+        .iter()
+        .find(|name| !opts.keys.iter().any(|key| key.name == **name))
+    {
+        return Err(eyre::eyre!(
+            "required signer `{}` isn't one of configured public keys",
+            unknown
+        ));
+    }
+    let keys = opts
+        .keys
+        .iter()
+        .map(|key| {
+            p256::ecdsa::VerifyingKey::from_public_key_pem(&key.pem)
+                .map(|verifying_key| (key.name.as_str(), verifying_key))
+                .map_err(|err| eyre::eyre!("invalid public key `{}`: {}", key.name, err))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let signatures = fetch(&pinned, digest, &opts.source)?;
+    let mut signers = vec![];
+    for (name, key) in keys {
+        for signed in &signatures {
+            match signed.invalid_reason(digest, &key) {
+                None => {
+                    signers.push(name.to_string());
+                    break;
+                }
+                Some(reason) => tracing::debug!("signature isn't valid for `{}`: {}", name, reason),
+            }
+        }
+    }
+    spans::record_duration(&span, started);
+    if signers.is_empty() {
+        return Err(eyre::eyre!(
+            "none of {} signature(s) of `{}` is valid for any of configured public keys",
+            signatures.len(),
+            docker_image
+        ));
+    }
+    span.record("signers", signers.join(",").as_str());
+    let missing = required_signers
+        .iter()
+        .filter(|name| !signers.contains(name))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(eyre::eyre!(
+            "`{}` isn't signed by required signers: {}",
+            docker_image,
+            missing.join(", ")
+        ));
+    }
+    reporter.report(
+        Message::new()
+            .title("image signed by:")
+            .normal(signers.join(", ")),
+    );
+    Ok(signers)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    use p256::ecdsa::signature::Signer;
+    use p256::pkcs8::EncodePublicKey;
+
+    use super::{Opts, PublicKey, Source};
+    use crate::logic::internal::oci_layout::sha256_digest;
+
+    const IMAGE_DIGEST: &str =
+        "sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+
+    fn key(seed: u8) -> (p256::ecdsa::SigningKey, String) {
+        let signing_key =
+            p256::ecdsa::SigningKey::from_bytes(&[seed; 32].into()).expect("valid scalar");
+        let pem = signing_key
+            .verifying_key()
+            .to_public_key_pem(p256::pkcs8::LineEnding::LF)
+            .expect("no error");
+        (signing_key, pem)
+    }
+
+    /// blobs of signatures manifest, signed by `signing_key`, and the manifest itself
+    fn signatures(
+        signing_key: &p256::ecdsa::SigningKey,
+        digest: &str,
+    ) -> (Vec<(String, Vec<u8>)>, Vec<u8>) {
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "critical": {
+                "identity": {"docker-reference": "docker.io/sourcescan/cargo-near"},
+                "image": {"docker-manifest-digest": digest},
+                "type": "cosign container image signature",
+            },
+            "optional": null,
+        }))
+        .expect("no error");
+        let signature: p256::ecdsa::Signature = signing_key.sign(&payload);
+        let signature = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            signature.to_der().as_bytes(),
+        );
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "layers": [{
+                "mediaType": super::SIMPLE_SIGNING_MEDIA_TYPE,
+                "digest": sha256_digest(&payload),
+                "size": payload.len(),
+                "annotations": {super::SIGNATURE_ANNOTATION: signature},
+            }],
+        }))
+        .expect("no error");
+        (vec![(sha256_digest(&payload), payload)], manifest)
+    }
+
+    fn write_layout(dir: &std::path::Path, blobs: &[(String, Vec<u8>)], manifest: &[u8]) {
+        let blobs_dir = dir.join("blobs/sha256");
+        std::fs::create_dir_all(&blobs_dir).expect("no error");
+        let manifest_digest = sha256_digest(manifest);
+        for (digest, contents) in blobs
+            .iter()
+            .chain([(manifest_digest.clone(), manifest.to_vec())].iter())
+        {
+            std::fs::write(
+                blobs_dir.join(digest.strip_prefix("sha256:").expect("sha256")),
+                contents,
+            )
+            .expect("no error");
+        }
+        let index = serde_json::json!({
+            "schemaVersion": 2,
+            "manifests": [{
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": manifest_digest,
+                "size": manifest.len(),
+                "annotations": {
+                    super::REF_NAME_ANNOTATION: super::signature_tag(IMAGE_DIGEST).expect("sha256"),
+                },
+            }],
+        });
+        std::fs::write(dir.join("index.json"), index.to_string()).expect("no error");
+    }
+
+    #[test]
+    fn test_verify_oci_layout() -> eyre::Result<()> {
+        let (signing_key, pem) = key(7);
+        let (_, other_pem) = key(8);
+        let keys = vec![
+            PublicKey {
+                name: "sourcescan".into(),
+                pem,
+            },
+            PublicKey {
+                name: "other".into(),
+                pem: other_pem,
+            },
+        ];
+        let image = format!("sourcescan/cargo-near:0.13.4-rust-1.85.0@{}", IMAGE_DIGEST);
+
+        let tempdir = tempfile::tempdir()?;
+        let (blobs, manifest) = signatures(&signing_key, IMAGE_DIGEST);
+        write_layout(tempdir.path(), &blobs, &manifest);
+        let opts = Opts {
+            keys: keys.clone(),
+            source: Source::OciLayout(tempdir.path().to_path_buf()),
+        };
+        let reporter = crate::reporter::Silent;
+
+        assert_eq!(
+            super::verify(&image, &opts, &["sourcescan".into()], &reporter)?,
+            vec!["sourcescan".to_string()]
+        );
+        let err = super::verify(&image, &opts, &["other".into()], &reporter).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("isn't signed by required signers: other")
+        );
+        assert!(super::verify(&image, &opts, &["unknown".into()], &reporter).is_err());
+
+        let opts = Opts {
+            keys: keys[1..].to_vec(),
+            ..opts
+        };
+        assert!(super::verify(&image, &opts, &[], &reporter).is_err());
+
+        // signature of another image
+        let tempdir = tempfile::tempdir()?;
+        let other_digest = format!("sha256:{}", "1".repeat(64));
+        let (blobs, manifest) = signatures(&signing_key, &other_digest);
+        write_layout(tempdir.path(), &blobs, &manifest);
+        let opts = Opts {
+            keys,
+            source: Source::OciLayout(tempdir.path().to_path_buf()),
+        };
+        assert!(super::verify(&image, &opts, &[], &reporter).is_err());
+        Ok(())
+    }
+
+    /// local registry stand-in, which requires an anonymous bearer token
+    fn fake_registry(
+        blobs: Vec<(String, Vec<u8>)>,
+        manifest: Vec<u8>,
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("no error");
+        let address = listener.local_addr().expect("no error").to_string();
+        let realm = format!("http://{}/token", address);
+        let handle = std::thread::spawn(move || {
+            let mut requests = vec![];
+            // manifest 401 → token → manifest → payload blob
+            for stream in listener.incoming().take(4) {
+                let mut stream = stream.expect("no error");
+                let mut reader = BufReader::new(stream.try_clone().expect("no error"));
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).expect("no error");
+                let mut authorized = false;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).expect("no error");
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    authorized |= header.to_lowercase().starts_with("authorization: bearer t");
+                }
+                let path = request_line.split(' ').nth(1).expect("path").to_string();
+                requests.push(path.clone());
+                let (status, headers, body) = if path.starts_with("/token") {
+                    ("200 OK", String::new(), br#"{"token":"t"}"#.to_vec())
+                } else if !authorized {
+                    let challenge = format!(
+                        "WWW-Authenticate: Bearer realm=\"{}\",service=\"fake\"\r\n",
+                        realm
+                    );
+                    ("401 Unauthorized", challenge, vec![])
+                } else if path.contains("/manifests/") {
+                    ("200 OK", String::new(), manifest.clone())
+                } else {
+                    let digest = path.rsplit('/').next().expect("digest");
+                    let blob = blobs
+                        .iter()
+                        .find(|(blob_digest, _)| blob_digest == digest)
+                        .map(|(_, blob)| blob.clone())
+                        .expect("known blob");
+                    ("200 OK", String::new(), blob)
+                };
+                let mut response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    headers,
+                    body.len()
+                )
+                .into_bytes();
+                response.extend(body);
+                stream.write_all(&response).expect("no error");
+                let _ = reader.read_to_end(&mut vec![]);
+            }
+            requests
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_verify_registry() -> eyre::Result<()> {
+        let (signing_key, pem) = key(7);
+        let (blobs, manifest) = signatures(&signing_key, IMAGE_DIGEST);
+        let (address, handle) = fake_registry(blobs, manifest);
+
+        let image = format!("{}/near/cargo-near:tag@{}", address, IMAGE_DIGEST);
+        let opts = Opts {
+            keys: vec![PublicKey {
+                name: "sourcescan".into(),
+                pem,
+            }],
+            source: Source::Registry,
+        };
+        let signers = super::verify(&image, &opts, &[], &crate::reporter::Silent)?;
+        assert_eq!(signers, vec!["sourcescan".to_string()]);
+
+        let requests = handle.join().expect("no error");
+        let tag = super::signature_tag(IMAGE_DIGEST)?;
+        assert_eq!(
+            requests[0],
+            format!("/v2/near/cargo-near/manifests/{}", tag)
+        );
+        assert!(requests[1].starts_with("/token?"));
+        assert_eq!(requests[2], requests[0]);
+        assert!(requests[3].starts_with("/v2/near/cargo-near/blobs/sha256:"));
+        Ok(())
+    }
+}
@@ -1,86 +1,76 @@
-use colored::Colorize;
-
-use crate::pretty_print::quiet_println;
+use crate::reporter::{Message, Reporter};
 
 pub fn handle_io_error<T>(
     command: &std::process::Command,
     command_result: std::io::Result<T>,
     report: eyre::Report,
-    quiet: bool,
+    reporter: &dyn Reporter,
 ) -> eyre::Result<T> {
     match command_result {
         Ok(result) => Ok(result),
         Err(io_err) if io_err.kind() == std::io::ErrorKind::NotFound => {
-            quiet_println!(quiet,);
-            quiet_println!(quiet, "{}", "`docker` executable isn't available".yellow());
-            print::installation_links(quiet);
+            reporter.separator();
+            reporter.report(Message::new().warning("`docker` executable isn't available"));
+            print::installation_links(reporter);
             Err(report)
         }
         Err(io_err) => {
-            quiet_println!(quiet,);
-            quiet_println!(
-                quiet,
-                "{}",
-                format!(
-                    "Error obtaining status from executing command `{:?}`",
-                    command
-                )
-                .yellow()
-            );
-            quiet_println!(quiet, "{}", format!("Error `{:?}`", io_err).yellow());
+            reporter.separator();
+            reporter.report(Message::new().warning(format!(
+                "Error obtaining status from executing command `{:?}`",
+                command
+            )));
+            reporter.report(Message::new().warning(format!("Error `{:?}`", io_err)));
             Err(report)
         }
     }
 }
 
 pub mod print {
-    use colored::Colorize;
     use std::process::Command;
 
-    use crate::pretty_print::quiet_println;
+    use crate::reporter::{Message, Reporter};
 
-    pub(crate) fn installation_links(quiet: bool) {
+    pub(crate) fn installation_links(reporter: &dyn Reporter) {
         match std::env::consts::OS {
             "linux" => {
-                quiet_println!(
-                    quiet,
-                    "{} {}",
-                    "Please, follow instructions to correctly install Docker Engine on".cyan(),
-                    "https://docs.docker.com/engine/install/".magenta()
+                reporter.report(
+                    Message::new()
+                        .hint("Please, follow instructions to correctly install Docker Engine on")
+                        .link("https://docs.docker.com/engine/install/"),
                 );
                 if is_wsl_linux() {
-                    quiet_println!(quiet,);
-                    quiet_println!(
-                        quiet,
-                        "{} {}",
-                        "Also the following page may be helpful as you're running linux in WSL "
-                            .cyan(),
-                        "https://docs.docker.com/desktop/wsl".magenta(),
+                    reporter.separator();
+                    reporter.report(
+                        Message::new()
+                            .hint(
+                                "Also the following page may be helpful as you're running linux in WSL",
+                            )
+                            .link("https://docs.docker.com/desktop/wsl"),
                     );
                 }
             }
 
             "macos" => {
-                quiet_println!(
-                    quiet,
-                    "{} {}",
-                    "Please, follow instructions to correctly install Docker Desktop on".cyan(),
-                    "https://docs.docker.com/desktop/install/mac-install/".magenta()
+                reporter.report(
+                    Message::new()
+                        .hint("Please, follow instructions to correctly install Docker Desktop on")
+                        .link("https://docs.docker.com/desktop/install/mac-install/"),
                 );
             }
             "windows" => {
-                quiet_println!(
-                    quiet,
-                    "{} {}",
-                    "Please, follow instructions to correctly install Docker Desktop on".cyan(),
-                    "https://docs.docker.com/desktop/install/windows-install/".magenta()
+                reporter.report(
+                    Message::new()
+                        .hint("Please, follow instructions to correctly install Docker Desktop on")
+                        .link("https://docs.docker.com/desktop/install/windows-install/"),
                 );
             }
             _ => {
-                quiet_println!(quiet, "{} {}", 
-                "Please, make sure to follow instructions to correctly install Docker Engine/Desktop on".cyan(),
-                "https://docs.docker.com/engine/install/".magenta()
-            );
+                reporter.report(
+                    Message::new()
+                        .hint("Please, make sure to follow instructions to correctly install Docker Engine/Desktop on")
+                        .link("https://docs.docker.com/engine/install/"),
+                );
             }
         }
     }
@@ -99,23 +89,22 @@ pub mod print {
         }
         false
     }
-    pub(crate) fn linux_postinstall_steps(quiet: bool) {
-        quiet_println!(
-            quiet,
-            "{} {} {} `{}` {}",
-            "Please, pay special attention to".cyan(),
-            "https://docs.docker.com/engine/install/linux-postinstall/".magenta(),
-            "section regarding your".cyan(),
-            "permission denied".magenta(),
-            "problem".cyan(),
+    pub(crate) fn linux_postinstall_steps(reporter: &dyn Reporter) {
+        reporter.report(
+            Message::new()
+                .hint("Please, pay special attention to")
+                .link("https://docs.docker.com/engine/install/linux-postinstall/")
+                .hint("section regarding your")
+                .link("`permission denied`")
+                .hint("problem"),
         );
     }
     pub fn command_status(
         status: std::process::ExitStatus,
         command: std::process::Command,
-        quiet: bool,
+        reporter: &dyn Reporter,
     ) {
-        quiet_println!(quiet,);
+        reporter.separator();
         let command = {
             let mut args = vec![command.get_program().to_string_lossy().to_string()];
             args.extend(
@@ -126,14 +115,9 @@ pub mod print {
             args.join(" ")
         };
 
-        quiet_println!(
-            quiet,
-            "{}",
-            format!(
-                "See output above ↑↑↑.\nCommand `{}` failed with: {status}.",
-                command
-            )
-            .yellow()
-        );
+        reporter.report(Message::new().warning(format!(
+            "See output above ↑↑↑.\nCommand `{}` failed with: {status}.",
+            command
+        )));
     }
 }
@@ -0,0 +1,511 @@
+//! Minimal client of [Docker Engine API](https://docs.docker.com/reference/api/engine/)
+//! over a unix socket, which doesn't require `docker` executable.
+//!
+//! Only HTTP/1.1 with `Connection: close` is spoken, a fresh connection is opened per request.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+
+use eyre::{ContextCompat, WrapErr};
+
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// socket from `DOCKER_HOST=unix://...` if it's set, [DEFAULT_SOCKET] otherwise
+pub fn socket_from_env() -> eyre::Result<std::path::PathBuf> {
+    match std::env::var("DOCKER_HOST") {
+        Ok(docker_host) if !docker_host.is_empty() => {
+            let socket = docker_host.strip_prefix("unix://").wrap_err_with(|| {
+                format!(
+                    "only `unix://` DOCKER_HOST is supported by docker engine API client, got `{}`",
+                    docker_host
+                )
+            })?;
+            Ok(socket.into())
+        }
+        _ => Ok(DEFAULT_SOCKET.into()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    socket: std::path::PathBuf,
+}
+
+/// Error response of Engine API, carrying the real HTTP status code
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "docker engine API error {}: {}",
+            self.status, self.message
+        )
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+struct Response<R> {
+    status: u16,
+    body: Body<R>,
+}
+
+/// Response body, decoding `Transfer-Encoding: chunked` if needed
+enum Body<R> {
+    Chunked {
+        reader: R,
+        remaining_in_chunk: usize,
+        done: bool,
+    },
+    Plain(std::io::Take<R>),
+    UntilEof(R),
+}
+
+impl<R: BufRead> Read for Body<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Body::Plain(reader) => reader.read(buf),
+            Body::UntilEof(reader) => reader.read(buf),
+            Body::Chunked {
+                reader,
+                remaining_in_chunk,
+                done,
+            } => {
+                if *done {
+                    return Ok(0);
+                }
+                if *remaining_in_chunk == 0 {
+                    let mut size_line = String::new();
+                    reader.read_line(&mut size_line)?;
+                    let size_hex = size_line.trim().split(';').next().unwrap_or_default();
+                    let size = usize::from_str_radix(size_hex, 16).map_err(|err| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("invalid chunk size `{}`: {}", size_line.trim(), err),
+                        )
+                    })?;
+                    if size == 0 {
+                        *done = true;
+                        return Ok(0);
+                    }
+                    *remaining_in_chunk = size;
+                }
+                let max = buf.len().min(*remaining_in_chunk);
+                let read = reader.read(&mut buf[..max])?;
+                if read == 0 {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                *remaining_in_chunk -= read;
+                if *remaining_in_chunk == 0 {
+                    let mut crlf = String::new();
+                    reader.read_line(&mut crlf)?;
+                }
+                Ok(read)
+            }
+        }
+    }
+}
+
+/// Stream of a multiplexed (non-tty) container output frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl Client {
+    pub fn new(socket: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> eyre::Result<Response<BufReader<UnixStream>>> {
+        let mut stream = UnixStream::connect(&self.socket).wrap_err_with(|| {
+            format!(
+                "cannot connect to docker engine at `{}`",
+                self.socket.display()
+            )
+        })?;
+        let body = match body {
+            Some(body) => serde_json::to_vec(body)?,
+            None => vec![],
+        };
+        let head = format!(
+            "{} {} HTTP/1.1\r\nHost: docker\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            method,
+            path,
+            body.len()
+        );
+        tracing::debug!("engine API request: {} {}", method, path);
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&body)?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .wrap_err_with(|| format!("malformed HTTP status line `{}`", status_line.trim()))?;
+
+        let mut chunked = false;
+        let mut content_length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                break;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("transfer-encoding")
+                    && value.eq_ignore_ascii_case("chunked")
+                {
+                    chunked = true;
+                } else if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse::<u64>().ok();
+                }
+            }
+        }
+        let body = match (chunked, content_length) {
+            (true, _) => Body::Chunked {
+                reader,
+                remaining_in_chunk: 0,
+                done: false,
+            },
+            (false, Some(length)) => Body::Plain(reader.take(length)),
+            (false, None) => Body::UntilEof(reader),
+        };
+        Ok(Response { status, body })
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> eyre::Result<serde_json::Value> {
+        let mut response = self.send(method, path, body)?;
+        let mut bytes = vec![];
+        response.body.read_to_end(&mut bytes)?;
+        if !(200..300).contains(&response.status) {
+            return Err(api_error(response.status, &bytes).into());
+        }
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Ok(serde_json::Value::Null);
+        }
+        serde_json::from_slice(&bytes)
+            .wrap_err_with(|| format!("malformed JSON response of {} {}", method, path))
+    }
+
+    /// `POST /containers/create`, returns container id
+    /// `platform`, e.g. `linux/amd64`, selects the image variant to create the container from
+    pub fn create_container(
+        &self,
+        name: &str,
+        platform: Option<&str>,
+        body: &serde_json::Value,
+    ) -> eyre::Result<String> {
+        let mut path = format!("/containers/create?name={}", encode(name));
+        if let Some(platform) = platform {
+            path.push_str(&format!("&platform={}", encode(platform)));
+        }
+        let response = self.request("POST", &path, Some(body))?;
+        for warning in response["Warnings"].as_array().into_iter().flatten() {
+            tracing::warn!("docker engine: {}", warning);
+        }
+        response["Id"]
+            .as_str()
+            .map(str::to_string)
+            .wrap_err("`Id` missing in response of container create")
+    }
+
+    pub fn start_container(&self, id: &str) -> eyre::Result<()> {
+        self.request("POST", &format!("/containers/{}/start", id), None)?;
+        Ok(())
+    }
+
+    /// follows container's stdout/stderr until it stops, passing each frame to `sink`
+    pub fn follow_logs(
+        &self,
+        id: &str,
+        mut sink: impl FnMut(OutputStream, &[u8]),
+    ) -> eyre::Result<()> {
+        let mut response = self.send(
+            "GET",
+            &format!("/containers/{}/logs?follow=1&stdout=1&stderr=1", id),
+            None,
+        )?;
+        if !(200..300).contains(&response.status) {
+            let mut bytes = vec![];
+            response.body.read_to_end(&mut bytes)?;
+            return Err(api_error(response.status, &bytes).into());
+        }
+        let mut header = [0u8; 8];
+        loop {
+            match response.body.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            }
+            let stream = match header[0] {
+                2 => OutputStream::Stderr,
+                _ => OutputStream::Stdout,
+            };
+            let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let mut payload = vec![0u8; size];
+            response.body.read_exact(&mut payload)?;
+            sink(stream, &payload);
+        }
+        Ok(())
+    }
+
+    /// `POST /containers/{id}/wait`, returns container's exit code
+    pub fn wait_container(&self, id: &str) -> eyre::Result<i64> {
+        let response = self.request("POST", &format!("/containers/{}/wait", id), None)?;
+        if let Some(message) = response["Error"]["Message"].as_str() {
+            if !message.is_empty() {
+                return Err(eyre::eyre!(
+                    "waiting for container `{}` failed: {}",
+                    id,
+                    message
+                ));
+            }
+        }
+        response["StatusCode"]
+            .as_i64()
+            .wrap_err("`StatusCode` missing in response of container wait")
+    }
+
+    /// `GET /info` of docker daemon
+    pub fn info(&self) -> eyre::Result<serde_json::Value> {
+        self.request("GET", "/info", None)
+    }
+
+    /// `GET /version` of docker daemon, e.g. with `Os` and `Arch`
+    pub fn version(&self) -> eyre::Result<serde_json::Value> {
+        self.request("GET", "/version", None)
+    }
+
+    pub fn inspect_container(&self, id: &str) -> eyre::Result<serde_json::Value> {
+        self.request("GET", &format!("/containers/{}/json", id), None)
+    }
+
+    pub fn inspect_image(&self, image: &str) -> eyre::Result<serde_json::Value> {
+        self.request("GET", &format!("/images/{}/json", image), None)
+    }
+
+    /// value of `label` on a local `image`, if any
+    pub fn image_label(&self, image: &str, label: &str) -> eyre::Result<Option<String>> {
+        let inspect = self.inspect_image(image)?;
+        Ok(inspect["Config"]["Labels"][label]
+            .as_str()
+            .filter(|value| !value.is_empty())
+            .map(str::to_string))
+    }
+
+    /// `GET /containers/json` of all containers, including stopped ones, which have `label`
+    pub fn list_containers(&self, label: &str) -> eyre::Result<Vec<serde_json::Value>> {
+        let filters = serde_json::json!({ "label": [label] }).to_string();
+        let response = self.request(
+            "GET",
+            &format!("/containers/json?all=1&filters={}", encode(&filters)),
+            None,
+        )?;
+        Ok(response.as_array().cloned().unwrap_or_default())
+    }
+
+    pub fn remove_container(&self, id: &str) -> eyre::Result<()> {
+        self.request("DELETE", &format!("/containers/{}?force=1", id), None)?;
+        Ok(())
+    }
+
+    /// create → start → follow logs → wait → inspect, always removing the container afterwards;
+    /// returns exit code and the result of inspection
+    pub fn run_to_completion(
+        &self,
+        name: &str,
+        platform: Option<&str>,
+        body: &serde_json::Value,
+        sink: impl FnMut(OutputStream, &[u8]),
+    ) -> eyre::Result<(i64, serde_json::Value)> {
+        let id = self.create_container(name, platform, body)?;
+        let result = (|| {
+            self.start_container(&id)?;
+            self.follow_logs(&id, sink)?;
+            let exit_code = self.wait_container(&id)?;
+            let inspect = self.inspect_container(&id)?;
+            Ok((exit_code, inspect))
+        })();
+        if let Err(err) = self.remove_container(&id) {
+            tracing::warn!("failed to remove container `{}`: {:?}", name, err);
+        }
+        result
+    }
+}
+
+fn api_error(status: u16, body: &[u8]) -> ApiError {
+    let message = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string());
+    ApiError { status, message }
+}
+
+/// percent-encodes a query parameter value
+fn encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::net::UnixListener;
+
+    use super::{ApiError, Client, OutputStream};
+
+    /// serves canned responses to consecutive connections, recording request lines
+    fn fake_engine(
+        responses: Vec<String>,
+    ) -> (
+        tempfile::TempDir,
+        Client,
+        std::thread::JoinHandle<Vec<String>>,
+    ) {
+        let dir = tempfile::tempdir().expect("no error");
+        let socket = dir.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket).expect("no error");
+        let handle = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for response in responses {
+                let (stream, _) = listener.accept().expect("no error");
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).expect("no error");
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).expect("no error");
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = header.to_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().expect("no error");
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).expect("no error");
+                requests.push(request_line.trim().to_string());
+                reader
+                    .into_inner()
+                    .write_all(response.as_bytes())
+                    .expect("no error");
+            }
+            requests
+        });
+        (dir, Client::new(socket), handle)
+    }
+
+    fn json_response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    fn chunked_logs_response(frames: &[(u8, &str)]) -> String {
+        let mut payload = vec![];
+        for (stream, text) in frames {
+            payload.extend([*stream, 0, 0, 0]);
+            payload.extend((text.len() as u32).to_be_bytes());
+            payload.extend(text.as_bytes());
+        }
+        // split into 2 chunks at an arbitrary point, crossing a frame boundary
+        let (first, second) = payload.split_at(5);
+        let mut response = String::from("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        for chunk in [first, second] {
+            response.push_str(&format!("{:x}\r\n", chunk.len()));
+            response.push_str(std::str::from_utf8(chunk).expect("ascii test payload"));
+            response.push_str("\r\n");
+        }
+        response.push_str("0\r\n\r\n");
+        response
+    }
+
+    #[test]
+    fn test_run_to_completion() -> eyre::Result<()> {
+        let (_dir, client, handle) = fake_engine(vec![
+            json_response("201 Created", r#"{"Id":"abc","Warnings":[]}"#),
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+            chunked_logs_response(&[(1, "compiling\n"), (2, "warning\n")]),
+            json_response("200 OK", r#"{"StatusCode":3}"#),
+            json_response("200 OK", r#"{"State":{"ExitCode":3,"OOMKilled":false}}"#),
+            "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+        ]);
+
+        let mut logs = vec![];
+        let (exit_code, inspect) = client.run_to_completion(
+            "near-verify-rs-test",
+            Some("linux/amd64"),
+            &serde_json::json!({"Image": "image"}),
+            |stream, payload| logs.push((stream, String::from_utf8_lossy(payload).to_string())),
+        )?;
+        assert_eq!(exit_code, 3);
+        assert_eq!(inspect["State"]["OOMKilled"], false);
+        assert_eq!(
+            logs,
+            vec![
+                (OutputStream::Stdout, "compiling\n".to_string()),
+                (OutputStream::Stderr, "warning\n".to_string())
+            ]
+        );
+        let requests = handle.join().expect("no error");
+        assert_eq!(
+            requests,
+            vec![
+                "POST /containers/create?name=near-verify-rs-test&platform=linux%2Famd64 HTTP/1.1",
+                "POST /containers/abc/start HTTP/1.1",
+                "GET /containers/abc/logs?follow=1&stdout=1&stderr=1 HTTP/1.1",
+                "POST /containers/abc/wait HTTP/1.1",
+                "GET /containers/abc/json HTTP/1.1",
+                "DELETE /containers/abc?force=1 HTTP/1.1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_api_error() {
+        let (_dir, client, _handle) = fake_engine(vec![json_response(
+            "404 Not Found",
+            r#"{"message":"No such image: sourcescan/cargo-near:missing"}"#,
+        )]);
+
+        let err = client
+            .create_container("name", None, &serde_json::json!({}))
+            .unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!(api_error.status, 404);
+        assert_eq!(
+            api_error.message,
+            "No such image: sourcescan/cargo-near:missing"
+        );
+    }
+}
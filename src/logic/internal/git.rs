@@ -0,0 +1,131 @@
+//! thin wrapper around `git` executable, similar to how `docker` is invoked
+use eyre::{ContextCompat, WrapErr};
+
+pub fn command(repo_dir: &camino::Utf8Path, args: &[&str]) -> eyre::Result<String> {
+    let mut git_cmd = std::process::Command::new("git");
+    git_cmd.arg("-C").arg(repo_dir.as_str()).args(args);
+    execute(git_cmd)
+}
+
+fn execute(mut git_cmd: std::process::Command) -> eyre::Result<String> {
+    tracing::debug!("git command: {:?}", git_cmd);
+    let output = git_cmd
+        .output()
+        .wrap_err_with(|| format!("failed to execute `{:?}`", git_cmd))?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`{:?}` failed with {}: {}",
+            git_cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .wrap_err_with(|| format!("non UTF-8 output of `{:?}`", git_cmd))?;
+    Ok(stdout.trim_end().to_string())
+}
+
+pub fn toplevel(dir: &camino::Utf8Path) -> eyre::Result<camino::Utf8PathBuf> {
+    let toplevel = command(dir, &["rev-parse", "--show-toplevel"])?;
+    Ok(camino::Utf8PathBuf::from(toplevel))
+}
+
+pub fn head_commit(repo_dir: &camino::Utf8Path) -> eyre::Result<String> {
+    command(repo_dir, &["rev-parse", "--verify", "HEAD^{commit}"])
+}
+
+/// `git status --porcelain` entries: modified tracked files and untracked files,
+/// which aren't ignored
+pub fn status_entries(repo_dir: &camino::Utf8Path) -> eyre::Result<Vec<String>> {
+    let output = command(
+        repo_dir,
+        &["status", "--porcelain", "--untracked-files=all"],
+    )?;
+    Ok(output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// url of the remote, which current branch tracks, or the only/`origin` remote otherwise
+pub fn remote_url(repo_dir: &camino::Utf8Path) -> eyre::Result<String> {
+    let remote = match command(
+        repo_dir,
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+    ) {
+        Ok(upstream) => upstream
+            .split_once('/')
+            .map(|(remote, _branch)| remote.to_string()),
+        Err(_) => None,
+    };
+    let remote = match remote {
+        Some(remote) => remote,
+        None => {
+            let remotes = command(repo_dir, &["remote"])?;
+            let remotes = remotes.lines().collect::<Vec<_>>();
+            match remotes.as_slice() {
+                [single] => single.to_string(),
+                _ if remotes.contains(&"origin") => "origin".to_string(),
+                _ => {
+                    return Err(eyre::eyre!(
+                        "cannot choose a git remote of `{}` among {:?}",
+                        repo_dir,
+                        remotes
+                    ));
+                }
+            }
+        }
+    };
+    let url = command(repo_dir, &["remote", "get-url", &remote])?;
+    Some(url)
+        .filter(|url| !url.is_empty())
+        .wrap_err_with(|| format!("empty url of `{}` git remote", remote))
+}
+
+/// id of git tree object, which `git` would record for contents of `dir`, that has no `.git`;
+/// a throwaway git dir and index are used, so that `dir` isn't modified
+///
+/// `.gitignore` files within `dir` are respected, while user-global excludes and
+/// line ending conversions are disabled for the result to not depend on the calling user's config
+pub fn tree_hash(dir: &camino::Utf8Path) -> eyre::Result<String> {
+    let git_dir = tempfile::Builder::new()
+        .prefix("near-verify-rs-tree-hash-")
+        .tempdir()?;
+    let git_dir_arg = format!("--git-dir={}", git_dir.path().display());
+    let work_tree_arg = format!("--work-tree={}", dir);
+    let config = [
+        "-c",
+        "core.autocrlf=false",
+        "-c",
+        "core.excludesFile=",
+        "-c",
+        "core.fileMode=true",
+        "-c",
+        "core.symlinks=true",
+    ];
+
+    let mut init_cmd = std::process::Command::new("git");
+    init_cmd
+        .args(["init", "--quiet", "--bare"])
+        .arg(git_dir.path());
+    execute(init_cmd)?;
+
+    let mut add_cmd = std::process::Command::new("git");
+    add_cmd
+        .arg(&git_dir_arg)
+        .arg(&work_tree_arg)
+        .args(config)
+        .args(["add", "--all", "--", "."]);
+    add_cmd.current_dir(dir);
+    execute(add_cmd)?;
+
+    let mut write_tree_cmd = std::process::Command::new("git");
+    write_tree_cmd.arg(&git_dir_arg).arg("write-tree");
+    execute(write_tree_cmd)
+}
@@ -0,0 +1,170 @@
+//! Reading of [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md),
+//! either a directory or a tarball of it, and of OCI content descriptors in general
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+pub const INDEX: &str = "index.json";
+pub const INDEX_MEDIA_TYPES: [&str; 2] = [
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+];
+pub const MANIFEST_MEDIA_TYPES: [&str; 2] = [
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+/// annotation with the tag of a manifest in `index.json`
+#[cfg(feature = "signature")]
+pub const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+/// image index, e.g. `index.json` of a layout
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Index {
+    pub manifests: Vec<Descriptor>,
+}
+
+/// image manifest
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    #[serde(default)]
+    pub config: Option<Descriptor>,
+    #[cfg(feature = "signature")]
+    pub layers: Vec<Descriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    pub media_type: String,
+    pub digest: String,
+    #[cfg(feature = "signature")]
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// `sha256:<hex>` digest of `contents`
+pub fn sha256_digest(contents: &[u8]) -> String {
+    format!(
+        "sha256:{}",
+        hex::encode(<sha2::Sha256 as sha2::Digest>::digest(contents))
+    )
+}
+
+/// checks that `contents` of a blob, obtained from `origin`, have `digest`
+pub fn verify_digest(digest: &str, contents: &[u8], origin: &str) -> eyre::Result<()> {
+    if !digest.starts_with("sha256:") {
+        return Err(eyre::eyre!(
+            "only sha256 blobs are supported, got `{}`",
+            digest
+        ));
+    }
+    let actual = sha256_digest(contents);
+    if actual != digest {
+        return Err(eyre::eyre!(
+            "blob `{}` in {} has digest `{}`",
+            digest,
+            origin,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+/// files of an OCI image layout, read by their path relative to its root
+pub enum Layout<'a> {
+    Dir(&'a Path),
+    Tar {
+        path: &'a Path,
+        /// offset and size of contents of each regular file of the tarball, by its path
+        entries: BTreeMap<PathBuf, (u64, u64)>,
+    },
+}
+
+impl<'a> Layout<'a> {
+    /// a tarball is scanned once here, so that its files are then read without rescanning it
+    pub fn open(archive: &'a Path) -> eyre::Result<Self> {
+        if archive.is_dir() {
+            Ok(Self::Dir(archive))
+        } else if archive.is_file() {
+            let mut entries = BTreeMap::new();
+            let mut tarball = tar::Archive::new(std::fs::File::open(archive)?);
+            for entry in tarball.entries()? {
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let entry_path = entry.path()?;
+                let entry_path = entry_path.strip_prefix("./").unwrap_or(&entry_path);
+                entries.insert(
+                    entry_path.to_path_buf(),
+                    (entry.raw_file_position(), entry.size()),
+                );
+            }
+            Ok(Self::Tar {
+                path: archive,
+                entries,
+            })
+        } else {
+            Err(eyre::eyre!(
+                "image archive `{}` is neither a directory nor a file",
+                archive.display()
+            ))
+        }
+    }
+
+    pub fn read(&self, path: &str) -> eyre::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Dir(dir) => {
+                let path = dir.join(path);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                Ok(Some(std::fs::read(path)?))
+            }
+            Self::Tar {
+                path: tarball,
+                entries,
+            } => {
+                let Some(&(position, size)) = entries.get(Path::new(path)) else {
+                    return Ok(None);
+                };
+                let mut file = std::fs::File::open(tarball)?;
+                file.seek(std::io::SeekFrom::Start(position))?;
+                let mut contents = vec![];
+                file.take(size).read_to_end(&mut contents)?;
+                if contents.len() as u64 != size {
+                    return Err(eyre::eyre!(
+                        "`{}` is truncated in `{}`",
+                        path,
+                        tarball.display()
+                    ));
+                }
+                Ok(Some(contents))
+            }
+        }
+    }
+
+    /// parsed [INDEX], [None] if it's absent
+    pub fn index(&self) -> eyre::Result<Option<Index>> {
+        match self.read(INDEX)? {
+            Some(index) => Ok(Some(serde_json::from_slice(&index)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// contents of the blob, checked to have `digest`
+    pub fn read_blob(&self, digest: &str) -> eyre::Result<Vec<u8>> {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| eyre::eyre!("only sha256 blobs are supported, got `{}`", digest))?;
+        let contents = self
+            .read(&format!("blobs/sha256/{}", hex))?
+            .ok_or_else(|| eyre::eyre!("blob `{}` is missing in image archive", digest))?;
+        verify_digest(digest, &contents, "image archive")?;
+        Ok(contents)
+    }
+}
@@ -0,0 +1,141 @@
+//! Minimal client of [OCI distribution](https://github.com/opencontainers/distribution-spec/blob/main/spec.md)
+//! API for reading manifests and blobs, with anonymous bearer token authentication
+use crate::logic::internal::oci_layout::{MANIFEST_MEDIA_TYPES, verify_digest};
+use crate::types::image_reference::{DEFAULT_REGISTRY, ImageReference};
+
+/// API host of [DEFAULT_REGISTRY]
+const DEFAULT_REGISTRY_HOST: &str = "registry-1.docker.io";
+
+pub struct Client {
+    agent: ureq::Agent,
+    base_url: String,
+    repository: String,
+    token: std::cell::RefCell<Option<String>>,
+}
+
+impl Client {
+    /// client of repository of `image`; `localhost` and `127.0.0.1` registries are accessed
+    /// over plain http, the way `docker` treats them as insecure registries by default
+    pub fn new(image: &ImageReference) -> Self {
+        let host = match image.registry.as_str() {
+            DEFAULT_REGISTRY => DEFAULT_REGISTRY_HOST,
+            registry => registry,
+        };
+        let hostname = host.split(':').next().unwrap_or(host);
+        let scheme = match hostname {
+            "localhost" | "127.0.0.1" => "http",
+            _ => "https",
+        };
+        let agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Self {
+            agent,
+            base_url: format!("{}://{}/v2/{}", scheme, host, image.repository),
+            repository: image.repository.clone(),
+            token: Default::default(),
+        }
+    }
+
+    fn get(&self, path: &str, accept: &str) -> eyre::Result<Option<Vec<u8>>> {
+        let url = format!("{}/{}", self.base_url, path);
+        let mut authenticated = false;
+        loop {
+            let mut request = self.agent.get(&url).header("Accept", accept);
+            if let Some(ref token) = *self.token.borrow() {
+                request = request.header("Authorization", &format!("Bearer {}", token));
+            }
+            let mut response = request
+                .call()
+                .map_err(|err| eyre::eyre!("GET {} failed: {}", url, err))?;
+            let status = response.status().as_u16();
+            match status {
+                200 => return Ok(Some(response.body_mut().read_to_vec()?)),
+                404 => return Ok(None),
+                401 if !authenticated => {
+                    let challenge = response
+                        .headers()
+                        .get("www-authenticate")
+                        .and_then(|value| value.to_str().ok())
+                        .ok_or_else(|| {
+                            eyre::eyre!("GET {} is unauthorized without a challenge", url)
+                        })?
+                        .to_string();
+                    *self.token.borrow_mut() = Some(self.anonymous_token(&challenge)?);
+                    authenticated = true;
+                }
+                _ => {
+                    let body = response.body_mut().read_to_string().unwrap_or_default();
+                    return Err(eyre::eyre!(
+                        "GET {} failed with {}: {}",
+                        url,
+                        status,
+                        body.trim()
+                    ));
+                }
+            }
+        }
+    }
+
+    /// token of `Bearer realm="...",service="...",scope="..."` challenge
+    fn anonymous_token(&self, challenge: &str) -> eyre::Result<String> {
+        let params = challenge
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| eyre::eyre!("unsupported registry auth challenge `{}`", challenge))?;
+        let param = |name: &str| {
+            params.split(',').find_map(|param| {
+                let (key, value) = param.trim().split_once('=')?;
+                (key == name).then(|| value.trim_matches('"').to_string())
+            })
+        };
+        let realm = param("realm")
+            .ok_or_else(|| eyre::eyre!("no realm in auth challenge `{}`", challenge))?;
+        let scope =
+            param("scope").unwrap_or_else(|| format!("repository:{}:pull", self.repository));
+        let mut request = self.agent.get(&realm).query("scope", &scope);
+        if let Some(ref service) = param("service") {
+            request = request.query("service", service);
+        }
+        let mut response = request
+            .call()
+            .map_err(|err| eyre::eyre!("GET {} failed: {}", realm, err))?;
+        if response.status().as_u16() != 200 {
+            return Err(eyre::eyre!(
+                "GET {} failed with {}",
+                realm,
+                response.status()
+            ));
+        }
+        let body: serde_json::Value = serde_json::from_slice(&response.body_mut().read_to_vec()?)?;
+        body["token"]
+            .as_str()
+            .or_else(|| body["access_token"].as_str())
+            .map(str::to_string)
+            .ok_or_else(|| eyre::eyre!("no token in response of {}", realm))
+    }
+
+    /// manifest by tag or digest, [None] if it's absent; a manifest fetched by digest
+    /// is checked to have it
+    pub fn manifest(&self, reference: &str) -> eyre::Result<Option<Vec<u8>>> {
+        let manifest = self.get(
+            &format!("manifests/{}", reference),
+            &MANIFEST_MEDIA_TYPES.join(", "),
+        )?;
+        if let Some(ref manifest) = manifest {
+            if reference.starts_with("sha256:") {
+                verify_digest(reference, manifest, "registry")?;
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// blob, checked to have `digest`
+    pub fn blob(&self, digest: &str) -> eyre::Result<Vec<u8>> {
+        let blob = self
+            .get(&format!("blobs/{}", digest), "*/*")?
+            .ok_or_else(|| eyre::eyre!("blob `{}` is missing in registry", digest))?;
+        verify_digest(digest, &blob, "registry")?;
+        Ok(blob)
+    }
+}
@@ -0,0 +1,136 @@
+//! Rewriting of `build_environment` to be pulled and run through a registry mirror.
+//!
+//! Only the registry/repository part of a reference is rewritten, its tag and pinned digest
+//! are kept, so that the mirror has to serve the very same image. Whitelists are still
+//! checked against the original `build_environment` by
+//! [ContractSourceMetadata::validate](crate::types::contract_source_metadata::ContractSourceMetadata::validate).
+use serde::{Deserialize, Serialize};
+
+use crate::reporter::{Message, Reporter};
+use crate::types::image_reference::ImageReference;
+
+/// `from` prefix of normalized `registry/repository` name is replaced with `to`, e.g.
+/// `{"from": "docker.io/sourcescan", "to": "mirror.internal:5000/dockerhub/sourcescan"}`;
+/// prefixes match whole path components only
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorRule {
+    pub from: String,
+    pub to: String,
+}
+
+impl MirrorRule {
+    /// the rest of `name` after [Self::from], if it matches
+    fn strip<'a>(&self, name: &'a str) -> Option<&'a str> {
+        let from = self.from.trim_end_matches('/');
+        let rest = name.strip_prefix(from)?;
+        (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+    }
+}
+
+/// `image` rewritten by the rule with the longest matching [MirrorRule::from],
+/// [None] if no rule matches
+pub fn rewrite(
+    image: &ImageReference,
+    rules: &[MirrorRule],
+) -> eyre::Result<Option<ImageReference>> {
+    let name = image.name();
+    let Some((rule, rest)) = rules
+        .iter()
+        .filter_map(|rule| rule.strip(&name).map(|rest| (rule, rest)))
+        .max_by_key(|(rule, _)| rule.from.trim_end_matches('/').len())
+    else {
+        return Ok(None);
+    };
+    let mirrored_name = format!("{}{}", rule.to.trim_end_matches('/'), rest);
+    let mirrored: ImageReference = mirrored_name.parse().map_err(|err: eyre::Report| {
+        err.wrap_err(format!(
+            "mirror rule `{}` → `{}` rewrites `{}` into an invalid name",
+            rule.from, rule.to, name
+        ))
+    })?;
+    Ok(Some(ImageReference {
+        tag: image.tag.clone(),
+        digest: image.digest.clone(),
+        ..mirrored
+    }))
+}
+
+/// [rewrite] of `docker_image` reference; [None] if there are no rules or none of them matches
+pub fn apply(docker_image: &str, rules: &[MirrorRule]) -> eyre::Result<Option<String>> {
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let image: ImageReference = docker_image.parse()?;
+    Ok(rewrite(&image, rules)?.map(|mirrored| mirrored.to_string()))
+}
+
+/// [apply], reporting both the original and the mirrored references
+pub(crate) fn resolve(
+    docker_image: &str,
+    rules: &[MirrorRule],
+    span: &tracing::Span,
+    reporter: &dyn Reporter,
+) -> eyre::Result<String> {
+    match apply(docker_image, rules)? {
+        Some(mirrored) => {
+            reporter.report(
+                Message::new()
+                    .title("original image:")
+                    .normal(docker_image)
+                    .title("mirrored as:")
+                    .normal(mirrored.as_str()),
+            );
+            span.record("mirrored_image", mirrored.as_str());
+            Ok(mirrored)
+        }
+        None => Ok(docker_image.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MirrorRule;
+    use crate::types::image_reference::ImageReference;
+
+    const DIGEST: &str = "sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+
+    fn rule(from: &str, to: &str) -> MirrorRule {
+        MirrorRule {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rewrite() -> eyre::Result<()> {
+        let image: ImageReference =
+            format!("sourcescan/cargo-near:0.13.4-rust-1.85.0@{}", DIGEST).parse()?;
+        let rules = [
+            rule("docker.io", "mirror.internal:5000/dockerhub"),
+            rule("docker.io/sourcescan/", "mirror.internal:5000/near"),
+            rule("docker.io/source", "mirror.internal:5000/partial"),
+        ];
+
+        let mirrored = super::rewrite(&image, &rules)?.expect("rule matches");
+        assert_eq!(
+            mirrored.to_string(),
+            format!(
+                "mirror.internal:5000/near/cargo-near:0.13.4-rust-1.85.0@{}",
+                DIGEST
+            )
+        );
+
+        let mirrored = super::rewrite(&image, &rules[..1])?.expect("rule matches");
+        assert_eq!(
+            mirrored.name(),
+            "mirror.internal:5000/dockerhub/sourcescan/cargo-near"
+        );
+        assert_eq!(mirrored.digest.as_deref(), Some(DIGEST));
+
+        assert_eq!(super::rewrite(&image, &rules[2..])?, None);
+        assert_eq!(super::apply(&image.to_string(), &[])?, None);
+
+        assert!(super::rewrite(&image, &[rule("docker.io", "mirror.internal/Dockerhub")]).is_err());
+        Ok(())
+    }
+}
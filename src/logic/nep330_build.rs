@@ -1,49 +1,253 @@
 use crate::logic::internal::docker_command;
 use crate::types::internal::container_paths;
-use colored::Colorize;
-use eyre::ContextCompat;
+use eyre::{ContextCompat, WrapErr};
 use std::io::IsTerminal;
-use std::process::Stdio;
-use std::{
-    process::{Command, ExitStatus},
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::process::Command;
+use std::sync::Arc;
 
 #[cfg(target_os = "linux")]
 use nix::unistd::{getgid, getuid};
 
 use crate::env_keys;
-use crate::pretty_print::{self, quiet_println};
+use crate::logic::container_cleanup;
+use crate::logic::docker_checks::pull_image::PullPolicy;
+use crate::logic::spans;
+use crate::logic::userns::Strategy;
+use crate::pretty_print;
+use crate::reporter::{self, Message, Reporter};
 use crate::types::contract_source_metadata::ContractSourceMetadata;
 
 pub const ERR_REPRODUCIBLE: &str = "Reproducible build in docker container failed.";
+mod copy_in_out;
+#[cfg(unix)]
+mod engine;
 mod output;
+pub mod perturbation;
 
-fn handle_docker_run_status(
+/// How `build_command` of [crate::types::contract_source_metadata::BuildInfo] is executed in container
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionForm {
+    /// shell-escaped `build_command` is passed to `/bin/bash -c`; the image is probed for a usable
+    /// shell with [crate::logic::docker_checks::shell::check] before the build
+    #[default]
+    Shell,
+    /// `build_command` is passed as container's argv as is, which doesn't require a shell
+    /// in the image, so that minimal/distroless build images are supported
+    Exec,
+}
+
+impl ExecutionForm {
+    /// arguments after the image of `docker run`, running `build_command`, `shell_escaped` with
+    /// [crate::logic::shell_escape_nep330_build_command] for [Self::Shell]
+    pub(crate) fn container_cmd<'a>(
+        &self,
+        build_command: &'a [String],
+        shell_escaped: &'a str,
+    ) -> Vec<&'a str> {
+        match self {
+            Self::Shell => crate::logic::docker_checks::shell::command(shell_escaped),
+            Self::Exec => build_command.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// How source code gets into a build container and output wasm gets out of it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Transfer {
+    /// `contract_source_workdir` is bind-mounted, which requires docker daemon to share
+    /// the host filesystem
+    #[default]
+    BindMount,
+    /// `docker create`, copy source in, `docker start --attach`, copy resolved output wasm out;
+    /// works with remote `DOCKER_HOST` or docker contexts
+    CopyInOut,
+}
+
+/// How containers are run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// `docker` CLI is executed
+    #[default]
+    Cli,
+    /// [Docker Engine API](https://docs.docker.com/reference/api/engine/) is requested directly
+    /// over a unix socket, which doesn't require `docker` CLI to be installed;
+    /// only supports [Transfer::BindMount] and `--env`, `--network`, `--hostname`
+    /// of [Opts::additional_docker_args]
+    EngineApi { socket: std::path::PathBuf },
+}
+
+impl Backend {
+    /// [Backend::EngineApi] with socket of `DOCKER_HOST=unix://...`, `/var/run/docker.sock` by default
+    #[cfg(unix)]
+    pub fn engine_api_from_env() -> eyre::Result<Self> {
+        Ok(Self::EngineApi {
+            socket: crate::logic::internal::engine_api::socket_from_env()?,
+        })
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn engine_client(
+        &self,
+    ) -> eyre::Result<Option<crate::logic::internal::engine_api::Client>> {
+        match self {
+            Self::Cli => Ok(None),
+            Self::EngineApi { socket } => Ok(Some(
+                crate::logic::internal::engine_api::Client::new(socket),
+            )),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn engine_client(&self) -> eyre::Result<Option<std::convert::Infallible>> {
+        match self {
+            Self::Cli => Ok(None),
+            Self::EngineApi { .. } => Err(eyre::eyre!(
+                "docker engine API backend is only supported on unix"
+            )),
+        }
+    }
+}
+
+/// Knobs of `docker run` invocation, which aren't part of [ContractSourceMetadata]
+#[derive(Debug, Clone)]
+pub struct Opts {
+    /// additional arguments, passed to `docker run` before the image, e.g. `--network=host`
+    pub additional_docker_args: Vec<String>,
+    /// [reporter::Human] by default
+    pub reporter: Arc<dyn Reporter>,
+    /// overrides `uid:gid` passed to `docker run -u`, which is the calling user's one on linux
+    /// and `1000:1000` elsewhere by default for [Transfer::BindMount],
+    /// and the owner of `contract_source_workdir` for [Transfer::CopyInOut]
+    pub user: Option<String>,
+    /// if set, [crate::logic::workdir_provenance::check] preflight is run before the build
+    pub workdir_provenance: Option<crate::logic::workdir_provenance::Expected>,
+    pub execution_form: ExecutionForm,
+    /// repository mount point inside of container; if not set, it's
+    /// [WhitelistEntry::repo_mount](crate::types::whitelist::WhitelistEntry::repo_mount)
+    /// of [Self::whitelist_entry], if that's set, or it's read from [crate::logic::repo_mount::LABEL] of the image, once it's pulled,
+    /// falling back to [crate::logic::NEP330_REPO_MOUNT]
+    pub repo_mount: Option<String>,
+    pub transfer: Transfer,
+    pub backend: Backend,
+    /// translation of `contract_source_workdir` into a path on docker daemon's host
+    /// for [Transfer::BindMount], when the verifier itself runs in a container
+    pub path_mapping: crate::logic::path_mapping::PathMapping,
+    /// id of the local image of `build_environment`, e.g. [crate::logic::docker_checks::inspect::InspectedImage::id]
+    /// as returned by [crate::logic::docker_checks::pull_image::check_opts]; if set, containers are
+    /// run from it instead of `build_environment` reference, which may be re-tagged in between;
+    /// if not set, it's the id of the image [crate::logic::docker_checks::pull_image::check_opts]
+    /// pulls with [Self::pull_policy] for [Backend::Cli]
+    pub image_id: Option<String>,
+    /// platform of the build container, passed to `docker run --platform`; if not set, it's
+    /// [WhitelistEntry::platform](crate::types::whitelist::WhitelistEntry::platform)
+    /// of [Self::whitelist_entry], if that's set, or the platform of the local image
+    pub platform: Option<crate::logic::platform::Platform>,
+    /// policy `build_environment` is pulled with by [crate::logic::docker_checks::pull_image::check_opts]
+    /// before the build, unless [Self::image_id] is set, and which is passed to `docker run --pull`
+    /// as [PullPolicy::run_arg]; containers of [Backend::EngineApi] are never pulled
+    pub pull_policy: PullPolicy,
+    /// rules, by which `build_environment` is run through a registry mirror with the same digest,
+    /// e.g. the ones it was pulled with by [crate::logic::docker_checks::pull_image::check_opts];
+    /// not applied to [Self::image_id]
+    pub mirrors: Vec<crate::logic::mirror::MirrorRule>,
+    /// user namespace strategy of [Transfer::BindMount] container; if not set, it's selected
+    /// by rootless mode of the daemon with [crate::logic::userns::Strategy::select],
+    /// unless [Self::user] is set
+    pub userns: Option<crate::logic::userns::Strategy>,
+    /// entry of the whitelist `build_environment` was matched with, as returned by
+    /// [ContractSourceMetadata::validate]; its
+    /// [expected_labels](crate::types::whitelist::WhitelistEntry::expected_labels)
+    /// are checked on the local image before the build, whose labels are recorded into
+    /// `labels` span of [crate::logic::spans] either way, and so are its
+    /// [required_signers](crate::types::whitelist::WhitelistEntry::required_signers)
+    /// with `signature` opts
+    pub whitelist_entry: Option<crate::types::whitelist::WhitelistEntry>,
+    /// public keys and source of signatures of `build_environment`; if set, the image has to be
+    /// signed by at least one of the keys, see [crate::logic::docker_checks::signature::verify]
+    #[cfg(feature = "signature")]
+    pub signature: Option<crate::logic::docker_checks::signature::Opts>,
+}
+
+impl Opts {
+    /// [Self::repo_mount] and [Self::platform], which aren't set explicitly,
+    /// taken from [Self::whitelist_entry]
+    fn with_whitelist_entry_defaults(mut self) -> Self {
+        if let Some(ref entry) = self.whitelist_entry {
+            if self.repo_mount.is_none() {
+                self.repo_mount = entry.repo_mount.clone();
+            }
+            if self.platform.is_none() {
+                self.platform = entry.platform.clone();
+            }
+        }
+        self
+    }
+}
+
+impl Default for Opts {
+    fn default() -> Self {
+        Self {
+            additional_docker_args: vec![],
+            reporter: Arc::new(reporter::Human),
+            user: None,
+            workdir_provenance: None,
+            execution_form: ExecutionForm::default(),
+            repo_mount: None,
+            transfer: Transfer::default(),
+            backend: Backend::default(),
+            path_mapping: Default::default(),
+            image_id: None,
+            platform: None,
+            pull_policy: PullPolicy::default(),
+            mirrors: vec![],
+            userns: None,
+            whitelist_entry: None,
+            #[cfg(feature = "signature")]
+            signature: None,
+        }
+    }
+}
+
+fn resolve_output(
     contract_source_metadata: ContractSourceMetadata,
     contract_source_workdir: camino::Utf8PathBuf,
-    status: ExitStatus,
-    command: Command,
-    quiet: bool,
+    repo_mount: &str,
+    copy_out_of: Option<&str>,
+    reporter: &dyn Reporter,
 ) -> eyre::Result<camino::Utf8PathBuf> {
-    if status.success() {
-        let build_info = contract_source_metadata.build_info.as_ref().wrap_err(
-            "cannot be [Option::None] as per [ContractSourceMetadata::validate_meta] check",
+    let build_info = contract_source_metadata.build_info.as_ref().wrap_err(
+        "cannot be [Option::None] as per [ContractSourceMetadata::validate_meta] check",
+    )?;
+    let span = spans::output_resolution(match build_info.output_wasm_path {
+        Some(_) => "explicit_metadata",
+        None => "rust_legacy",
+    });
+    let _guard = span.enter();
+    let started = std::time::Instant::now();
+    let expected = match build_info.output_wasm_path {
+        Some(ref output_wasm_path) => output::explicit_metadata::expected_wasm_output_path(
+            output_wasm_path,
+            repo_mount,
+            contract_source_workdir.clone(),
+        ),
+        None => output::rust_legacy::expected_wasm_output_path(
+            contract_source_metadata.clone(),
+            contract_source_workdir.clone(),
+        ),
+    }?;
+    if let Some(container_name) = copy_out_of {
+        copy_in_out::copy_out(
+            container_name,
+            &contract_source_workdir,
+            repo_mount,
+            &expected.path,
+            reporter,
         )?;
-        match build_info.output_wasm_path {
-            Some(ref output_wasm_path) => output::explicit_metadata::wasm_output_path(
-                output_wasm_path,
-                contract_source_workdir,
-            ),
-            None => output::rust_legacy::wasm_output_path(
-                contract_source_metadata,
-                contract_source_workdir,
-            ),
-        }
-    } else {
-        docker_command::print::command_status(status, command, quiet);
-        Err(eyre::eyre!(ERR_REPRODUCIBLE))
     }
+    let wasm_path = expected.check()?;
+    span.record("wasm_path", wasm_path.as_str());
+    spans::record_duration(&span, started);
+    Ok(wasm_path)
 }
 
 pub fn run(
@@ -52,117 +256,551 @@ pub fn run(
     additional_docker_args: Vec<String>,
     quiet: bool,
 ) -> eyre::Result<camino::Utf8PathBuf> {
-    let (status, command) = run_inner(
+    run_opts(
+        contract_source_metadata,
+        contract_source_workdir,
+        Opts {
+            additional_docker_args,
+            reporter: reporter::from_quiet(quiet),
+            ..Default::default()
+        },
+    )
+}
+
+pub fn run_opts(
+    contract_source_metadata: ContractSourceMetadata,
+    contract_source_workdir: camino::Utf8PathBuf,
+    opts: Opts,
+) -> eyre::Result<camino::Utf8PathBuf> {
+    let mut opts = opts.with_whitelist_entry_defaults();
+    let reporter = opts.reporter.clone();
+    let build_info = contract_source_metadata.build_info.as_ref().wrap_err(
+        "cannot be [Option::None] as per [ContractSourceMetadata::validate_meta] check",
+    )?;
+    if let Some(ref expected) = opts.workdir_provenance {
+        crate::logic::workdir_provenance::check(build_info, &contract_source_workdir, expected)?;
+    }
+    if opts.transfer == Transfer::CopyInOut && opts.backend != Backend::Cli {
+        return Err(eyre::eyre!(
+            "copy-in/copy-out transfer isn't supported by docker engine API backend"
+        ));
+    }
+    // `build_environment` is pulled per [Opts::pull_policy] and verified to be the pinned image
+    // for the platform, so that labels are read from and the build runs in this very image
+    // rather than whatever is in the local cache; containers of [Backend::EngineApi] are never pulled
+    if opts.image_id.is_none() && opts.backend == Backend::Cli {
+        let pull_opts = crate::logic::docker_checks::pull_image::Opts {
+            allowed_platforms: match opts.platform {
+                Some(ref platform) => vec![platform.clone()],
+                None => crate::logic::docker_checks::pull_image::Opts::default().allowed_platforms,
+            },
+            platform: opts.platform.clone(),
+            pull_policy: opts.pull_policy,
+            mirrors: opts.mirrors.clone(),
+        };
+        let inspected = crate::logic::docker_checks::pull_image::check_opts(
+            &build_info.build_environment,
+            &pull_opts,
+            reporter.as_ref(),
+        )?;
+        opts.image_id = Some(inspected.id);
+    }
+    let mirrored = crate::logic::mirror::apply(&build_info.build_environment, &opts.mirrors)?;
+    let image = opts
+        .image_id
+        .as_deref()
+        .or(mirrored.as_deref())
+        .unwrap_or(&build_info.build_environment);
+    let repo_mount = crate::logic::repo_mount::resolve(
+        opts.repo_mount.clone(),
+        image,
+        &opts.backend,
+        reporter.as_ref(),
+    )?;
+    build_info.validate_output_wasm_path_on(&repo_mount)?;
+    // labels are reported and recorded into a span even without expected ones
+    let expected_labels = opts
+        .whitelist_entry
+        .as_ref()
+        .map(|entry| entry.expected_labels.clone())
+        .unwrap_or_default();
+    crate::logic::docker_checks::labels::verify_on(
+        image,
+        &expected_labels,
+        &opts.backend,
+        reporter.as_ref(),
+    )
+    .wrap_err_with(|| {
+        format!(
+            "Image `{}` doesn't match its whitelist entry",
+            build_info.build_environment
+        )
+    })?;
+    verify_signatures(build_info, &opts, reporter.as_ref())?;
+    warn_on_rust_toolchain_disagreement(build_info, &contract_source_workdir, reporter.as_ref());
+
+    let transfer = opts.transfer;
+    let userns = match (transfer, &opts.user) {
+        (Transfer::BindMount, None) => {
+            crate::logic::userns::resolve(opts.userns, &opts.backend, reporter.as_ref())
+        }
+        _ => opts.userns.unwrap_or(Strategy::UidPassthrough),
+    };
+    let container = run_inner(
         contract_source_metadata.clone(),
         contract_source_workdir.clone(),
-        additional_docker_args,
-        quiet,
+        &repo_mount,
+        userns,
+        opts,
     )?;
 
-    handle_docker_run_status(
+    let result = resolve_output(
         contract_source_metadata,
         contract_source_workdir,
-        status,
-        command,
-        quiet,
-    )
+        &repo_mount,
+        (transfer == Transfer::CopyInOut).then_some(container.name()),
+        reporter.as_ref(),
+    );
+    drop(container);
+    let wasm_path = result?;
+    crate::logic::userns::check_readable(&wasm_path, userns, reporter.as_ref())?;
+    Ok(wasm_path)
+}
+
+fn warn_on_rust_toolchain_disagreement(
+    build_info: &crate::types::contract_source_metadata::BuildInfo,
+    contract_source_workdir: &camino::Utf8Path,
+    reporter: &dyn Reporter,
+) {
+    let crate_dir = contract_source_workdir.join(&build_info.contract_path);
+    let disagreement = build_info
+        .build_environment_info()
+        .and_then(|info| info.rust_toolchain_disagreement(contract_source_workdir, &crate_dir));
+    match disagreement {
+        Ok(Some(disagreement)) => {
+            reporter.report(Message::new().warning(disagreement));
+        }
+        Ok(None) => {}
+        Err(err) => tracing::debug!("rust toolchain file isn't checked: {:?}", err),
+    }
+}
+
+/// checks signatures of `build_environment` with [Opts::signature], if set,
+/// and by [required_signers](crate::types::whitelist::WhitelistEntry::required_signers)
+/// of [Opts::whitelist_entry]
+#[cfg(feature = "signature")]
+fn verify_signatures(
+    build_info: &crate::types::contract_source_metadata::BuildInfo,
+    opts: &Opts,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    let required_signers = opts
+        .whitelist_entry
+        .as_ref()
+        .map(|entry| entry.required_signers.as_slice())
+        .unwrap_or_default();
+    let signature_opts = match opts.signature {
+        Some(ref signature_opts) => signature_opts,
+        None if required_signers.is_empty() => return Ok(()),
+        None => {
+            return Err(eyre::eyre!(
+                "whitelist entry of `{}` requires signers {}, but no public keys are configured \
+                to verify signatures with",
+                build_info.build_environment,
+                required_signers.join(", ")
+            ));
+        }
+    };
+    crate::logic::docker_checks::signature::verify(
+        &build_info.build_environment,
+        signature_opts,
+        required_signers,
+        reporter,
+    )?;
+    Ok(())
 }
 
+/// without `signature` feature, an entry with
+/// [required_signers](crate::types::whitelist::WhitelistEntry::required_signers) is refused
+#[cfg(not(feature = "signature"))]
+fn verify_signatures(
+    build_info: &crate::types::contract_source_metadata::BuildInfo,
+    opts: &Opts,
+    _reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    match opts.whitelist_entry {
+        Some(ref entry) if !entry.required_signers.is_empty() => Err(eyre::eyre!(
+            "whitelist entry of `{}` requires signers {}, but near-verify-rs is built \
+            without `signature` feature",
+            build_info.build_environment,
+            entry.required_signers.join(", ")
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// returns the guard of the container of a successful build, which is to be dropped
+/// once output wasm is resolved; a failed build is reported and results in [ERR_REPRODUCIBLE]
 fn run_inner(
     contract_source_metadata: ContractSourceMetadata,
     contract_source_workdir: camino::Utf8PathBuf,
-    additional_docker_args: Vec<String>,
-    quiet: bool,
-) -> eyre::Result<(ExitStatus, Command)> {
+    repo_mount: &str,
+    userns: Strategy,
+    opts: Opts,
+) -> eyre::Result<container_cleanup::Guard> {
+    let Opts {
+        additional_docker_args,
+        reporter,
+        user,
+        workdir_provenance: _,
+        execution_form,
+        repo_mount: _,
+        transfer,
+        backend,
+        path_mapping,
+        image_id,
+        platform,
+        pull_policy,
+        mirrors,
+        userns: _,
+        whitelist_entry: _,
+        #[cfg(feature = "signature")]
+            signature: _,
+    } = opts;
+    let engine_client = backend.engine_client()?;
     let build_info = contract_source_metadata.build_info.clone().wrap_err(
         "cannot be [Option::None] as per [ContractSourceMetadata::validate_meta] check",
     )?;
-    let mut docker_cmd: Command = {
-        // Platform-specific UID/GID retrieval
+    let span = spans::container_run(&build_info.build_environment, &build_info.contract_path);
+    let _guard = span.enter();
+    let image = match image_id {
+        Some(image_id) => {
+            span.record("image_id", image_id.as_str());
+            image_id
+        }
+        None => crate::logic::mirror::resolve(
+            &build_info.build_environment,
+            &mirrors,
+            &span,
+            reporter.as_ref(),
+        )?,
+    };
+    let image = image.as_str();
+    let platform = crate::logic::platform::resolve(platform, image, &backend, reporter.as_ref());
+    let platform_arg = platform.as_ref().map(ToString::to_string);
+    if let Some(ref platform_arg) = platform_arg {
+        span.record("platform", platform_arg.as_str());
+    }
+    let started = std::time::Instant::now();
+    if execution_form == ExecutionForm::Shell {
+        crate::logic::docker_checks::shell::check_on(
+            image,
+            platform.as_ref(),
+            pull_policy,
+            &backend,
+            reporter.as_ref(),
+        )
+        .wrap_err(ERR_REPRODUCIBLE)?;
+    }
+    let docker_container_name = format!("near-verify-rs-{}", crate::logic::unique_suffix());
+    span.record("container_name", docker_container_name.as_str());
+    let container_guard =
+        container_cleanup::Guard::new(docker_container_name.clone(), backend.clone());
+    let container_labels = container_cleanup::labels(&build_info.build_environment);
+    let uid_gid = match user {
+        Some(user) => user,
+        None => userns.user(default_uid_gid(transfer, &contract_source_workdir)?),
+    };
+    span.record("userns", userns.as_str());
+    let userns_arg = userns
+        .userns_mode()
+        .map(|mode| format!("--userns={}", mode));
 
-        // reason for this mapping is that on Linux the volume is mounted natively,
-        // and thus the unprivileged user inside Docker container should be able to write
-        // to the mounted folder that has the host user permissions,
-        // not specifying this mapping results in UID=Docker-User owned files created in host system
-        #[cfg(target_os = "linux")]
-        let uid_gid = format!("{}:{}", getuid(), getgid());
-        #[cfg(not(target_os = "linux"))]
-        let uid_gid = "1000:1000".to_string();
-
-        let docker_container_name = {
-            // Cross-platform process ID and timestamp
-            let pid = std::process::id().to_string();
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-                .to_string();
-            format!("near-verify-rs-{}-{}", timestamp, pid)
+    let host_workdir = match transfer {
+        Transfer::BindMount => crate::logic::path_mapping::resolve(
+            &path_mapping,
+            &contract_source_workdir,
+            reporter.as_ref(),
+        )?,
+        Transfer::CopyInOut => contract_source_workdir.clone(),
+    };
+    let container_paths = container_paths::Paths::compute(&build_info, repo_mount, host_workdir)?;
+
+    let docker_env_args = contract_source_metadata.docker_env_args();
+    let shell_escaped_cargo_cmd =
+        crate::logic::shell_escape_nep330_build_command(build_info.build_command.clone());
+    reporter.report(
+        Message::new()
+            .title("build command in container:")
+            .normal(shell_escaped_cargo_cmd.as_str()),
+    );
+    reporter.separator();
+
+    let container_cmd =
+        execution_form.container_cmd(&build_info.build_command, &shell_escaped_cargo_cmd);
+
+    #[cfg(unix)]
+    if let Some(client) = engine_client {
+        let args = [docker_env_args, additional_docker_args].concat();
+        let container = engine::Container {
+            image,
+            platform: platform_arg.as_deref(),
+            labels: &container_labels,
+            user: &uid_gid,
+            userns_mode: userns.userns_mode(),
+            workdir: &container_paths.crate_path,
+            binds: vec![&container_paths.host_volume_arg],
+            args: &args,
+            cmd: container_cmd,
         };
-        let container_paths =
-            container_paths::Paths::compute(&build_info, contract_source_workdir)?;
-
-        let docker_env_args = contract_source_metadata.docker_env_args();
-        let shell_escaped_cargo_cmd =
-            crate::logic::shell_escape_nep330_build_command(build_info.build_command);
-        quiet_println!(
-            quiet,
-            "{} {}",
-            "build command in container:".green(),
-            shell_escaped_cargo_cmd
-        );
-        quiet_println!(quiet,);
+        let exit_code = engine::run(
+            &client,
+            &docker_container_name,
+            &container,
+            reporter.as_ref(),
+        )
+        .wrap_err(ERR_REPRODUCIBLE)?;
+        span.record("exit_code", exit_code);
+        spans::record_duration(&span, started);
+        // container is removed by [engine::run] either way
+        container_guard.removed();
+        if exit_code != 0 {
+            return Err(eyre::eyre!(ERR_REPRODUCIBLE));
+        }
+        return Ok(container_guard);
+    }
+    #[cfg(not(unix))]
+    let _ = engine_client;
 
+    let label_args = container_labels
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>();
+    let mut docker_cmd: Command = {
         let docker_args = {
-            let mut docker_args = vec![
-                "-u",
-                &uid_gid,
-                "--name",
-                &docker_container_name,
-                "--volume",
-                &container_paths.host_volume_arg,
-                "--rm",
-                "--workdir",
-                &container_paths.crate_path,
-            ];
-            let stdin_is_terminal = std::io::stdin().is_terminal();
-            tracing::debug!("input device is a tty: {}", stdin_is_terminal);
-            if stdin_is_terminal
-                && std::env::var(env_keys::nonspec::SERVER_DISABLE_INTERACTIVE).is_err()
-            {
-                docker_args.push("-it");
+            let mut docker_args = vec!["-u", &uid_gid, "--name", &docker_container_name];
+            if let Some(ref userns_arg) = userns_arg {
+                docker_args.push(userns_arg);
             }
+            for label_arg in &label_args {
+                docker_args.extend(["--label", label_arg]);
+            }
+            match transfer {
+                Transfer::BindMount => {
+                    docker_args.extend(["--volume", &container_paths.host_volume_arg, "--rm"]);
+                    let stdin_is_terminal = std::io::stdin().is_terminal();
+                    tracing::debug!("input device is a tty: {}", stdin_is_terminal);
+                    if stdin_is_terminal
+                        && std::env::var(env_keys::nonspec::SERVER_DISABLE_INTERACTIVE).is_err()
+                    {
+                        docker_args.push("-it");
+                    }
+                }
+                // container is removed after output wasm is copied out of it
+                Transfer::CopyInOut => {}
+            }
+            docker_args.extend(["--workdir", &container_paths.crate_path]);
+            if let Some(ref platform_arg) = platform_arg {
+                docker_args.extend(["--platform", platform_arg]);
+            }
+            docker_args.extend(["--pull", pull_policy.run_arg()]);
 
             docker_args.extend(docker_env_args.iter().map(|string| string.as_str()));
             docker_args.extend(additional_docker_args.iter().map(|string| string.as_str()));
-            docker_args.extend(vec![&build_info.build_environment, "/bin/bash", "-c"]);
-
-            docker_args.push(&shell_escaped_cargo_cmd);
+            docker_args.push(image);
+            docker_args.extend(container_cmd);
             docker_args
         };
 
         let mut docker_cmd = Command::new("docker");
-        docker_cmd.arg("run");
+        match transfer {
+            Transfer::BindMount => docker_cmd.arg("run"),
+            Transfer::CopyInOut => docker_cmd.arg("create"),
+        };
         docker_cmd.args(docker_args);
         docker_cmd
     };
     tracing::info!(
         target: "near_teach_me",
-        parent: &tracing::Span::none(),
         "Docker command:\n{}",
         pretty_print::indent_payload(&format!("{:#?}", docker_cmd))
     );
 
-    if quiet {
-        docker_cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    if transfer == Transfer::CopyInOut {
+        let create_output = docker_cmd.output();
+        let create_output = docker_command::handle_io_error(
+            &docker_cmd,
+            create_output,
+            eyre::eyre!(ERR_REPRODUCIBLE),
+            reporter.as_ref(),
+        )?;
+        if !create_output.status.success() {
+            reporter.report(Message::new().warning(String::from_utf8_lossy(&create_output.stderr)));
+            docker_command::print::command_status(
+                create_output.status,
+                docker_cmd,
+                reporter.as_ref(),
+            );
+            return Err(eyre::eyre!(ERR_REPRODUCIBLE));
+        }
+        if let Err(err) = copy_in_out::copy_in(
+            &docker_container_name,
+            &contract_source_workdir,
+            repo_mount,
+            reporter.as_ref(),
+        ) {
+            return Err(err.wrap_err(ERR_REPRODUCIBLE));
+        }
+        docker_cmd = Command::new("docker");
+        docker_cmd.args(["start", "--attach", &docker_container_name]);
     }
+
+    reporter::configure_child(reporter.as_ref(), &mut docker_cmd);
     let status_result = docker_cmd.status();
     let status = docker_command::handle_io_error(
         &docker_cmd,
         status_result,
         eyre::eyre!(ERR_REPRODUCIBLE),
-        quiet,
-    )?;
+        reporter.as_ref(),
+    )
+    .and_then(|status| {
+        spans::record_exit_code(&span, &status);
+        spans::record_duration(&span, started);
+        if status.success() {
+            Ok(())
+        } else {
+            docker_command::print::command_status(status, docker_cmd, reporter.as_ref());
+            Err(eyre::eyre!(ERR_REPRODUCIBLE))
+        }
+    });
+    // a failed `docker run --rm` may have left the container running, e.g. when
+    // `docker` CLI is interrupted, so it's only known to be removed on success
+    status?;
+    if transfer == Transfer::BindMount {
+        container_guard.removed();
+    }
+    Ok(container_guard)
+}
+
+/// Platform-specific UID/GID retrieval
+fn default_uid_gid(
+    transfer: Transfer,
+    #[allow(unused_variables)] contract_source_workdir: &camino::Utf8Path,
+) -> eyre::Result<String> {
+    match transfer {
+        // reason for this mapping is that on Linux the volume is mounted natively,
+        // and thus the unprivileged user inside Docker container should be able to write
+        // to the mounted folder that has the host user permissions,
+        // not specifying this mapping results in UID=Docker-User owned files created in host system
+        Transfer::BindMount => {
+            #[cfg(target_os = "linux")]
+            let uid_gid = format!("{}:{}", getuid(), getgid());
+            #[cfg(not(target_os = "linux"))]
+            let uid_gid = "1000:1000".to_string();
+            Ok(uid_gid)
+        }
+        // ownership of files is preserved by `docker cp --archive`, so the build runs
+        // as the owner of copied in files
+        Transfer::CopyInOut => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let metadata = std::fs::metadata(contract_source_workdir)?;
+                Ok(format!("{}:{}", metadata.uid(), metadata.gid()))
+            }
+            #[cfg(not(unix))]
+            Ok("1000:1000".to_string())
+        }
+    }
+}
 
-    Ok((status, docker_cmd))
+#[cfg(test)]
+mod tests {
+    use super::{ExecutionForm, Opts, verify_signatures};
+    use crate::reporter::Silent;
+    use crate::types::contract_source_metadata::BuildInfo;
+    use crate::types::whitelist::WhitelistEntry;
+
+    #[test]
+    fn test_verify_signatures_without_keys() {
+        let build_info = BuildInfo {
+            build_environment: "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2".to_string(),
+            ..Default::default()
+        };
+        let opts = |required_signers: Vec<String>| Opts {
+            whitelist_entry: Some(WhitelistEntry {
+                expected_docker_image: "sourcescan/cargo-near".to_string(),
+                required_signers,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(verify_signatures(&build_info, &opts(vec![]), &Silent).is_ok());
+
+        let err =
+            verify_signatures(&build_info, &opts(vec!["near".to_string()]), &Silent).unwrap_err();
+        assert!(err.to_string().contains("requires signers near"));
+    }
+
+    #[test]
+    fn test_container_cmd() {
+        let build_command = [
+            "cargo",
+            "near",
+            "build",
+            "non-reproducible-wasm",
+            "--locked",
+        ]
+        .map(String::from);
+        let shell_escaped = crate::logic::shell_escape_nep330_build_command(build_command.to_vec());
+
+        assert_eq!(
+            ExecutionForm::Exec.container_cmd(&build_command, &shell_escaped),
+            [
+                "cargo",
+                "near",
+                "build",
+                "non-reproducible-wasm",
+                "--locked"
+            ]
+        );
+        // entrypoint of the image is kept, as `cargo near` runs it
+        assert_eq!(
+            ExecutionForm::Shell.container_cmd(&build_command, &shell_escaped),
+            [
+                "/bin/bash",
+                "-c",
+                "cargo near build non-reproducible-wasm --locked"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_whitelist_entry_defaults() -> eyre::Result<()> {
+        let entry = WhitelistEntry {
+            expected_docker_image: "dj8yfo/sourcescan".to_string(),
+            repo_mount: Some("/workspace/code".to_string()),
+            platform: Some("linux/arm64".parse()?),
+            ..Default::default()
+        };
+        let opts = Opts {
+            whitelist_entry: Some(entry.clone()),
+            ..Default::default()
+        }
+        .with_whitelist_entry_defaults();
+        assert_eq!(opts.repo_mount.as_deref(), Some("/workspace/code"));
+        assert_eq!(opts.platform, entry.platform);
+
+        // explicit knobs take precedence over the entry
+        let opts = Opts {
+            whitelist_entry: Some(entry),
+            repo_mount: Some("/home/near/code".to_string()),
+            platform: Some("linux/amd64".parse()?),
+            ..Default::default()
+        }
+        .with_whitelist_entry_defaults();
+        assert_eq!(opts.repo_mount.as_deref(), Some("/home/near/code"));
+        assert_eq!(opts.platform, Some("linux/amd64".parse()?));
+        Ok(())
+    }
 }
@@ -0,0 +1,126 @@
+//! [super::Transfer::CopyInOut] helpers: source is copied into a created container
+//! and the resolved output wasm is copied back out of it, so that no filesystem
+//! has to be shared with the docker daemon (remote `DOCKER_HOST`, docker contexts).
+use eyre::WrapErr;
+
+use crate::logic::internal::docker_command;
+use crate::reporter::Reporter;
+
+fn docker(args: &[&str], reporter: &dyn Reporter) -> eyre::Result<()> {
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.args(args);
+    tracing::debug!("docker command: {:?}", docker_cmd);
+    let output_result = docker_cmd.output();
+    let output = docker_command::handle_io_error(
+        &docker_cmd,
+        output_result,
+        eyre::eyre!("`{:?}` failed", docker_cmd),
+        reporter,
+    )?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`{:?}` failed with {}: {}",
+            docker_cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// copies contents of `contract_source_workdir` into `repo_mount` of a created, not yet started container,
+/// preserving ownership (`-a`), so that it matches the user the build runs as
+pub fn copy_in(
+    container_name: &str,
+    contract_source_workdir: &camino::Utf8Path,
+    repo_mount: &str,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    let source = format!("{}/.", contract_source_workdir);
+    let destination = format!("{}:{}", container_name, repo_mount);
+    docker(&["cp", "--archive", &source, &destination], reporter).wrap_err_with(|| {
+        format!(
+            "failed to copy `{}` into container",
+            contract_source_workdir
+        )
+    })
+}
+
+/// copies `wasm_path`, which is a host path under `contract_source_workdir`, from the corresponding
+/// path under `repo_mount` of a finished container
+pub fn copy_out(
+    container_name: &str,
+    contract_source_workdir: &camino::Utf8Path,
+    repo_mount: &str,
+    wasm_path: &camino::Utf8Path,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    let container_path = container_path(contract_source_workdir, repo_mount, wasm_path)?;
+    if let Some(parent) = wasm_path.parent() {
+        std::fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("failed to create directory `{}`", parent))?;
+    }
+    let source = format!("{}:{}", container_name, container_path);
+    docker(&["cp", &source, wasm_path.as_str()], reporter)
+        .wrap_err_with(|| format!("failed to copy `{}` out of container", container_path))
+}
+
+fn container_path(
+    contract_source_workdir: &camino::Utf8Path,
+    repo_mount: &str,
+    wasm_path: &camino::Utf8Path,
+) -> eyre::Result<String> {
+    // legacy rust output path is computed from canonicalized `cargo metadata` paths
+    let workdir = dunce::canonicalize(contract_source_workdir)
+        .wrap_err_with(|| format!("failed to canonicalize path: {}", contract_source_workdir))?;
+    let relative = wasm_path
+        .as_std_path()
+        .strip_prefix(&workdir)
+        .or_else(|_| {
+            wasm_path
+                .strip_prefix(contract_source_workdir)
+                .map(|p| p.as_std_path())
+        })
+        .map_err(|_| {
+            eyre::eyre!(
+                "output wasm `{}` isn't under `{}`, it cannot be copied out of container",
+                wasm_path,
+                contract_source_workdir
+            )
+        })?;
+    let mut path = unix_path::PathBuf::from(repo_mount);
+    for component in relative.components() {
+        let component = component.as_os_str().to_str().ok_or_else(|| {
+            eyre::eyre!("non UTF-8 component of output wasm path `{}`", wasm_path)
+        })?;
+        path.push(component);
+    }
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| eyre::eyre!("non UTF-8 container path of `{}`", wasm_path))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_container_path() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let workdir = camino::Utf8PathBuf::from_path_buf(tempdir.path().to_path_buf())
+            .map_err(|err| eyre::eyre!("{:?}", err))?;
+        let wasm_path = workdir.join("target").join("near").join("contract.wasm");
+
+        assert_eq!(
+            super::container_path(&workdir, "/home/near/code", &wasm_path)?,
+            "/home/near/code/target/near/contract.wasm"
+        );
+        assert!(
+            super::container_path(
+                &workdir,
+                "/home/near/code",
+                camino::Utf8Path::new("/elsewhere/contract.wasm")
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! [super::Backend::EngineApi] helpers: `docker run` arguments are translated into a body
+//! of `POST /containers/create`, output of container is routed according to
+//! [crate::reporter::Reporter::child_output]
+use std::io::Write;
+
+use crate::logic::internal::engine_api::{Client, OutputStream};
+use crate::reporter::{ChildOutput, Message, Reporter};
+
+/// container to be created, in terms of `docker run` arguments
+pub(super) struct Container<'a> {
+    pub image: &'a str,
+    /// passed as a query parameter of `POST /containers/create` rather than in the body
+    pub platform: Option<&'a str>,
+    /// e.g. [crate::logic::container_cleanup::labels]
+    pub labels: &'a [(&'static str, String)],
+    pub user: &'a str,
+    /// `--userns`, e.g. [crate::logic::userns::Strategy::userns_mode]
+    pub userns_mode: Option<&'a str>,
+    pub workdir: &'a str,
+    pub binds: Vec<&'a str>,
+    /// `--env` args, produced by [crate::types::contract_source_metadata::ContractSourceMetadata::docker_env_args],
+    /// followed by additional args of [super::Opts]
+    pub args: &'a [String],
+    pub cmd: Vec<&'a str>,
+}
+
+impl Container<'_> {
+    /// only `--env`/`-e`, `--network` and `--hostname`/`-h` of `args` have an equivalent
+    /// in the body, other args are rejected
+    pub fn create_body(&self) -> eyre::Result<serde_json::Value> {
+        let mut env = vec![];
+        let mut network = None;
+        let mut hostname = None;
+
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || -> eyre::Result<String> {
+                match inline_value.clone() {
+                    Some(value) => Ok(value),
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| eyre::eyre!("`{}` docker arg requires a value", flag)),
+                }
+            };
+            match flag {
+                "--env" | "-e" => env.push(value()?),
+                "--network" | "--net" => network = Some(value()?),
+                "--hostname" | "-h" => hostname = Some(value()?),
+                _ => {
+                    return Err(eyre::eyre!(
+                        "docker arg `{}` isn't supported by docker engine API backend, \
+                        consider using `docker` CLI backend",
+                        arg
+                    ));
+                }
+            }
+        }
+
+        let mut body = serde_json::json!({
+            "Image": self.image,
+            "Labels": self.labels.iter().cloned().collect::<std::collections::BTreeMap<_, _>>(),
+            "User": self.user,
+            "WorkingDir": self.workdir,
+            "Env": env,
+            "Cmd": self.cmd,
+            "HostConfig": {
+                "Binds": self.binds,
+            },
+        });
+        if let Some(network) = network {
+            body["HostConfig"]["NetworkMode"] = network.into();
+        }
+        if let Some(hostname) = hostname {
+            body["Hostname"] = hostname.into();
+        }
+        if let Some(userns_mode) = self.userns_mode {
+            body["HostConfig"]["UsernsMode"] = userns_mode.into();
+        }
+        Ok(body)
+    }
+}
+
+/// destination of container output, equivalent to [crate::reporter::configure_child]
+pub(super) fn output_sink(reporter: &dyn Reporter) -> impl FnMut(OutputStream, &[u8]) + use<> {
+    let child_output = reporter.child_output();
+    move |stream, payload| {
+        let result = match (child_output, stream) {
+            (ChildOutput::Suppress, _) => Ok(()),
+            (ChildOutput::Inherit, OutputStream::Stdout) => std::io::stdout().write_all(payload),
+            (ChildOutput::Inherit, OutputStream::Stderr) | (ChildOutput::Stderr, _) => {
+                std::io::stderr().write_all(payload)
+            }
+        };
+        if let Err(err) = result {
+            tracing::debug!("cannot write container output: {:?}", err);
+        }
+    }
+}
+
+/// runs `container` to completion, reporting its state on a non-zero exit code
+pub(super) fn run(
+    client: &Client,
+    name: &str,
+    container: &Container,
+    reporter: &dyn Reporter,
+) -> eyre::Result<i64> {
+    let body = container.create_body()?;
+    tracing::info!(
+        target: "near_teach_me",
+        "Docker engine API container create:\n{}",
+        crate::pretty_print::indent_payload(&serde_json::to_string_pretty(&body)?)
+    );
+    let (exit_code, inspect) =
+        client.run_to_completion(name, container.platform, &body, output_sink(reporter))?;
+    if exit_code != 0 {
+        reporter.separator();
+        reporter.report(
+            Message::new()
+                .failure("Container")
+                .normal(name)
+                .failure(format!("exited with code {}", exit_code)),
+        );
+        if inspect["State"]["OOMKilled"].as_bool() == Some(true) {
+            reporter
+                .report(Message::new().failure("Container was killed for running out of memory"));
+        }
+    }
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Container;
+
+    #[test]
+    fn test_create_body() -> eyre::Result<()> {
+        let args = [
+            "--env",
+            "NEP330_BUILD_INFO_CONTRACT_PATH=",
+            "--network=none",
+            "-h",
+            "builder",
+        ]
+        .map(String::from);
+        let container = Container {
+            image: "sourcescan/cargo-near:0.13.4-rust-1.85.0",
+            platform: Some("linux/amd64"),
+            labels: &[("near-verify-rs.pid", "42".to_string())],
+            user: "1000:1000",
+            userns_mode: None,
+            workdir: "/home/near/code",
+            binds: vec!["/tmp/src:/home/near/code:z"],
+            args: &args,
+            cmd: vec!["cargo", "near", "build"],
+        };
+        assert_eq!(
+            container.create_body()?,
+            serde_json::json!({
+                "Image": "sourcescan/cargo-near:0.13.4-rust-1.85.0",
+                "Labels": {"near-verify-rs.pid": "42"},
+                "User": "1000:1000",
+                "WorkingDir": "/home/near/code",
+                "Env": ["NEP330_BUILD_INFO_CONTRACT_PATH="],
+                "Cmd": ["cargo", "near", "build"],
+                "Hostname": "builder",
+                "HostConfig": {
+                    "Binds": ["/tmp/src:/home/near/code:z"],
+                    "NetworkMode": "none",
+                },
+            })
+        );
+
+        let container = Container {
+            userns_mode: Some("keep-id"),
+            ..container
+        };
+        assert_eq!(
+            container.create_body()?["HostConfig"]["UsernsMode"],
+            "keep-id"
+        );
+
+        let args = ["--privileged".to_string()];
+        let container = Container {
+            args: &args,
+            ..container
+        };
+        assert!(container.create_body().is_err());
+        Ok(())
+    }
+}
@@ -50,10 +50,10 @@ pub mod rust_legacy {
         Ok(path)
     }
 
-    pub fn wasm_output_path(
+    pub fn expected_wasm_output_path(
         contract_source_metadata: ContractSourceMetadata,
         contract_source_workdir: camino::Utf8PathBuf,
-    ) -> eyre::Result<camino::Utf8PathBuf> {
+    ) -> eyre::Result<common::Expected> {
         let manifest_path = {
             let manifest_path = manifest_path(contract_source_metadata, contract_source_workdir)?;
             ManifestPath::try_from(manifest_path).wrap_err("Assumption about compiling a rust crate in docker container is invalid: manifest file not found")?
@@ -64,47 +64,59 @@ pub mod rust_legacy {
         let path = crate_metadata.get_legacy_cargo_near_output_path()?;
         tracing::info!(
             target: "near_teach_me",
-            parent: &tracing::Span::none(),
+            wasm_path = %path,
             "assumed artifact result path for a rust crate docker build: `{}`", path
         );
 
-        common::path_sane_check(&path, "rust crate")?;
-
-        Ok(path)
+        Ok(common::Expected {
+            path,
+            descriptor: "rust crate",
+        })
     }
 }
 
 pub mod explicit_metadata {
     use eyre::ContextCompat;
 
-    use crate::logic::NEP330_REPO_MOUNT;
-
     use super::common;
 
-    pub fn wasm_output_path(
+    pub fn expected_wasm_output_path(
         output_wasm_path: &str,
+        repo_mount: &str,
         contract_source_workdir: camino::Utf8PathBuf,
-    ) -> eyre::Result<camino::Utf8PathBuf> {
-        let base = camino::Utf8PathBuf::from(NEP330_REPO_MOUNT);
+    ) -> eyre::Result<common::Expected> {
+        let base = camino::Utf8PathBuf::from(repo_mount);
         let subpath = camino::Utf8PathBuf::from(output_wasm_path);
 
         let relative_path = pathdiff::diff_utf8_paths(&subpath, &base).wrap_err(format!(
             "cannot compute contract output pathdiff from mount point {}",
-            NEP330_REPO_MOUNT
+            repo_mount
         ))?;
         let wasm_path = contract_source_workdir.join(relative_path);
-        common::path_sane_check(&wasm_path, "generic nep330 1.3.0 compliant")?;
-        Ok(wasm_path)
+        Ok(common::Expected {
+            path: wasm_path,
+            descriptor: "generic nep330 1.3.0 compliant",
+        })
     }
 }
 
-mod common {
+pub mod common {
     use crate::types::internal::legacy_rust::metadata::EXPECTED_EXTENSION;
 
-    pub(super) fn path_sane_check(
-        path: &camino::Utf8PathBuf,
-        descriptor: &str,
-    ) -> eyre::Result<()> {
+    /// host path, where output wasm is expected to be found after a build
+    pub struct Expected {
+        pub path: camino::Utf8PathBuf,
+        pub descriptor: &'static str,
+    }
+
+    impl Expected {
+        pub fn check(self) -> eyre::Result<camino::Utf8PathBuf> {
+            path_sane_check(&self.path, self.descriptor)?;
+            Ok(self.path)
+        }
+    }
+
+    fn path_sane_check(path: &camino::Utf8PathBuf, descriptor: &str) -> eyre::Result<()> {
         if !path.exists() {
             return Err(eyre::eyre!(
                 "assumed artifact result path for a {} docker build not found: `{}`",
@@ -0,0 +1,338 @@
+//! Diagnostic mode, which re-runs a build with controlled perturbations of the
+//! verifier's environment and reports, which of them changed the resulting checksum.
+//!
+//! Each build (including the baseline one) runs on a fresh copy of `contract_source_workdir`,
+//! so that no build reuses artifacts of a previous one.
+use eyre::WrapErr;
+
+use crate::reporter::{Message, Reporter};
+use crate::types::contract_source_metadata::ContractSourceMetadata;
+use crate::types::sha256_checksum::SHA256Checksum;
+
+use super::Opts;
+
+/// A single deviation of the verifier's environment from the baseline build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Perturbation {
+    /// source is copied to a host path of different name and depth
+    HostWorkdir,
+    /// `docker run -u uid:gid` mapping differs from the calling user's one
+    UidGid { uid: u32, gid: u32 },
+    /// `TZ` env variable inside of container
+    Timezone(String),
+    /// `LANG` and `LC_ALL` env variables inside of container
+    Locale(String),
+    /// `docker run --hostname`
+    Hostname(String),
+}
+
+impl Perturbation {
+    /// a set of perturbations, each of which is expected not to affect a reproducible build
+    pub fn default_set() -> Vec<Self> {
+        vec![
+            Self::HostWorkdir,
+            Self::UidGid {
+                uid: 4242,
+                gid: 4242,
+            },
+            Self::Timezone("Pacific/Kiritimati".to_string()),
+            // turkish locale is notorious for case conversion surprises (dotless `ı`)
+            Self::Locale("tr_TR.UTF-8".to_string()),
+            Self::Hostname("perturbed-verifier".to_string()),
+        ]
+    }
+
+    fn docker_args(&self) -> Vec<String> {
+        match self {
+            Self::HostWorkdir | Self::UidGid { .. } => vec![],
+            Self::Timezone(tz) => vec!["--env".to_string(), format!("TZ={}", tz)],
+            Self::Locale(locale) => vec![
+                "--env".to_string(),
+                format!("LANG={}", locale),
+                "--env".to_string(),
+                format!("LC_ALL={}", locale),
+            ],
+            Self::Hostname(hostname) => vec!["--hostname".to_string(), hostname.clone()],
+        }
+    }
+}
+
+impl std::fmt::Display for Perturbation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HostWorkdir => write!(f, "host workdir"),
+            Self::UidGid { uid, gid } => write!(f, "uid:gid {}:{}", uid, gid),
+            Self::Timezone(tz) => write!(f, "TZ={}", tz),
+            Self::Locale(locale) => write!(f, "LANG={}", locale),
+            Self::Hostname(hostname) => write!(f, "hostname {}", hostname),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// checksum is the same as the baseline one
+    Reproduced,
+    Changed {
+        checksum: SHA256Checksum,
+    },
+    BuildFailed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub perturbation: Perturbation,
+    pub verdict: Verdict,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub baseline: SHA256Checksum,
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Report {
+    /// perturbations, which resulted in a different checksum or a failed build
+    pub fn not_reproduced(&self) -> impl Iterator<Item = &Outcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.verdict != Verdict::Reproduced)
+    }
+
+    pub fn is_robust(&self) -> bool {
+        self.not_reproduced().next().is_none()
+    }
+}
+
+/// Builds the contract once without perturbations and once per each of `perturbations`.
+///
+/// Error is returned only if the baseline build fails, failures of perturbed builds
+/// are reported as [Verdict::BuildFailed].
+pub fn run(
+    contract_source_metadata: ContractSourceMetadata,
+    contract_source_workdir: camino::Utf8PathBuf,
+    opts: Opts,
+    perturbations: Vec<Perturbation>,
+) -> eyre::Result<Report> {
+    let reporter = opts.reporter.clone();
+    // removed on drop
+    let staging_tempdir = tempfile::Builder::new()
+        .prefix("near-verify-rs-perturbation-")
+        .tempdir()?;
+    let staging_root = camino::Utf8PathBuf::from_path_buf(staging_tempdir.path().to_path_buf())
+        .map_err(|err| eyre::eyre!("non UTF-8 temporary directory {:?}", err))?;
+    let default_workdir = staging_root.join("workdir");
+
+    reporter.report(
+        Message::new()
+            .title("perturbation:")
+            .normal("baseline build"),
+    );
+    let baseline = build(
+        &contract_source_metadata,
+        &contract_source_workdir,
+        &default_workdir,
+        opts.clone(),
+        false,
+    )
+    .wrap_err("baseline build of perturbation check failed")?;
+
+    let mut outcomes = vec![];
+    for perturbation in perturbations {
+        reporter.report(
+            Message::new()
+                .title("perturbation:")
+                .hint(perturbation.to_string()),
+        );
+        let mut opts = opts.clone();
+        opts.additional_docker_args
+            .extend(perturbation.docker_args());
+        let (staging_workdir, world_writable) = match perturbation {
+            Perturbation::HostWorkdir => (
+                staging_root
+                    .join("relocated")
+                    .join("some other prefix")
+                    .join("workdir"),
+                false,
+            ),
+            Perturbation::UidGid { uid, gid } => {
+                opts.user = Some(format!("{}:{}", uid, gid));
+                // a foreign uid has to be able to write `target` directory into bind mount
+                (default_workdir.clone(), true)
+            }
+            _ => (default_workdir.clone(), false),
+        };
+
+        let verdict = match build(
+            &contract_source_metadata,
+            &contract_source_workdir,
+            &staging_workdir,
+            opts,
+            world_writable,
+        ) {
+            Ok(checksum) if checksum == baseline => Verdict::Reproduced,
+            Ok(checksum) => Verdict::Changed { checksum },
+            Err(err) => Verdict::BuildFailed {
+                error: format!("{:#}", err),
+            },
+        };
+        tracing::info!(
+            target: "near_teach_me",
+            "perturbation `{}` verdict: {:?}", perturbation, verdict
+        );
+        outcomes.push(Outcome {
+            perturbation,
+            verdict,
+        });
+    }
+    let report = Report { baseline, outcomes };
+    print_report(&report, reporter.as_ref());
+    Ok(report)
+}
+
+fn build(
+    contract_source_metadata: &ContractSourceMetadata,
+    contract_source_workdir: &camino::Utf8Path,
+    staging_workdir: &camino::Utf8Path,
+    opts: Opts,
+    world_writable: bool,
+) -> eyre::Result<SHA256Checksum> {
+    if staging_workdir.exists() {
+        // moving aside instead of removing, as artifacts of a build with a foreign uid
+        // may be not removable by the calling user; these are cleaned up on a best effort basis
+        // together with staging root
+        let mut used = staging_workdir.to_path_buf();
+        used.set_extension(format!("used-{}", crate::logic::unique_suffix()));
+        std::fs::rename(staging_workdir, &used)
+            .wrap_err_with(|| format!("failed to move aside `{}`", staging_workdir))?;
+    }
+    copy_dir_recursive(contract_source_workdir, staging_workdir, world_writable).wrap_err_with(
+        || {
+            format!(
+                "failed to copy `{}` to `{}`",
+                contract_source_workdir, staging_workdir
+            )
+        },
+    )?;
+    let wasm_path = super::run_opts(
+        contract_source_metadata.clone(),
+        staging_workdir.to_path_buf(),
+        opts,
+    )?;
+    crate::logic::compute_hash(wasm_path)
+}
+
+fn copy_dir_recursive(
+    from: &camino::Utf8Path,
+    to: &camino::Utf8Path,
+    world_writable: bool,
+) -> eyre::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in from.read_dir_utf8()? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir_recursive(entry.path(), &target, world_writable)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+            #[cfg(not(unix))]
+            std::fs::copy(entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+        #[cfg(unix)]
+        if world_writable && !file_type.is_symlink() {
+            make_world_writable(&target)?;
+        }
+    }
+    #[cfg(unix)]
+    if world_writable {
+        make_world_writable(to)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_world_writable(path: &camino::Utf8Path) -> eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    let mode = permissions.mode();
+    let executable = mode & 0o111 != 0;
+    permissions.set_mode(mode | if executable { 0o777 } else { 0o666 });
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+fn print_report(report: &Report, reporter: &dyn Reporter) {
+    reporter.separator();
+    reporter.report(
+        Message::new()
+            .title("baseline checksum:")
+            .normal(report.baseline.to_base58_string()),
+    );
+    for outcome in report.outcomes.iter() {
+        let message = match outcome.verdict {
+            Verdict::Reproduced => Message::new()
+                .title("  reproduced:")
+                .normal(outcome.perturbation.to_string()),
+            Verdict::Changed { ref checksum } => {
+                Message::new().failure("  changed:").normal(format!(
+                    "{} -> {}",
+                    outcome.perturbation,
+                    checksum.to_base58_string()
+                ))
+            }
+            Verdict::BuildFailed { ref error } => Message::new()
+                .warning("  build failed:")
+                .normal(format!("{}: {}", outcome.perturbation, error)),
+        };
+        reporter.report(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Perturbation;
+
+    #[test]
+    fn test_copy_dir_recursive() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = camino::Utf8PathBuf::from_path_buf(tempdir.path().to_path_buf())
+            .map_err(|err| eyre::eyre!("{:?}", err))?;
+        let from = root.join("from");
+        std::fs::create_dir_all(from.join("nested"))?;
+        std::fs::write(from.join("Cargo.toml"), "[package]")?;
+        std::fs::write(from.join("nested").join("lib.rs"), "")?;
+
+        let to = root.join("to").join("deeper");
+        super::copy_dir_recursive(&from, &to, true)?;
+        assert_eq!(std::fs::read_to_string(to.join("Cargo.toml"))?, "[package]");
+        assert!(to.join("nested").join("lib.rs").is_file());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(to.join("nested"))?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o777);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_docker_args() {
+        assert_eq!(
+            Perturbation::Hostname("host".into()).docker_args(),
+            vec!["--hostname", "host"]
+        );
+        assert_eq!(
+            Perturbation::Locale("tr_TR.UTF-8".into()).docker_args(),
+            vec!["--env", "LANG=tr_TR.UTF-8", "--env", "LC_ALL=tr_TR.UTF-8"]
+        );
+        assert!(Perturbation::HostWorkdir.docker_args().is_empty());
+    }
+}
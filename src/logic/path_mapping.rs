@@ -0,0 +1,271 @@
+//! Translation of local paths into paths on docker daemon's host, needed when the verifier
+//! itself runs in a container, which talks to the host daemon (Docker-in-Docker via a mounted
+//! socket), so that a local `contract_source_workdir` doesn't exist on the host as is.
+//!
+//! Only the source of `--volume` argument is translated, output wasm is resolved
+//! with local paths.
+use eyre::WrapErr;
+
+use crate::reporter::{Message, Reporter};
+
+/// `container` directory of the verifier is `host` directory on docker daemon's host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub container: camino::Utf8PathBuf,
+    pub host: camino::Utf8PathBuf,
+}
+
+/// filesystem `source`, e.g. `/dev/nvme0n1p2`, is mounted at `host` directory on docker daemon's host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilesystemMount {
+    pub source: String,
+    pub host: camino::Utf8PathBuf,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathMapping {
+    /// take precedence over detected ones
+    pub mappings: Vec<Mapping>,
+    /// detect mappings from bind mounts of `/proc/self/mountinfo` with [detect]
+    pub detect: bool,
+    /// host mount points of filesystems, which aren't mounted at `/` on docker daemon's host,
+    /// for [detect]; roots of bind mounts from other filesystems are taken as host paths
+    pub filesystems: Vec<FilesystemMount>,
+}
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// bind mounts of the current mount namespace
+///
+/// `root` of a mountinfo entry is relative to its source filesystem, so it's joined to the host
+/// mount point of the filesystem from `filesystems`; it's a host path as is for other filesystems,
+/// which is right if they are mounted at `/` on the host, as is the case for default docker
+/// data root and most bind-mounted host directories
+pub fn detect(filesystems: &[FilesystemMount]) -> eyre::Result<Vec<Mapping>> {
+    if !cfg!(target_os = "linux") {
+        return Ok(vec![]);
+    }
+    let mountinfo = std::fs::read_to_string(MOUNTINFO)
+        .wrap_err_with(|| format!("failed to read `{}`", MOUNTINFO))?;
+    Ok(parse_mountinfo(&mountinfo, filesystems))
+}
+
+fn parse_mountinfo(mountinfo: &str, filesystems: &[FilesystemMount]) -> Vec<Mapping> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            // `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw`
+            let mut fields = line.split_whitespace().skip(3);
+            let root = unescape(fields.next()?);
+            let mount_point = unescape(fields.next()?);
+            // not a bind mount of a subdirectory
+            if root == "/" || mount_point == "/" {
+                return None;
+            }
+            // optional fields are terminated by `-`, followed by filesystem type and source
+            let source = fields
+                .skip_while(|field| *field != "-")
+                .nth(2)
+                .map(unescape);
+            let host = match filesystems
+                .iter()
+                .find(|filesystem| Some(&filesystem.source) == source.as_ref())
+            {
+                Some(filesystem) => filesystem.host.join(root.trim_start_matches('/')),
+                None => root.into(),
+            };
+            Some(Mapping {
+                container: mount_point.into(),
+                host,
+            })
+        })
+        .collect()
+}
+
+/// mountinfo escapes space, tab, newline and backslash as `\ooo` octal
+fn unescape(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        let escaped = rest.get(index + 1..index + 4);
+        match escaped.and_then(|octal| u8::from_str_radix(octal, 8).ok()) {
+            Some(byte) => {
+                result.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// `path` under the longest matching [Mapping::container], translated to [Mapping::host];
+/// [None] if no mapping matches
+pub fn translate(path: &camino::Utf8Path, mappings: &[Mapping]) -> Option<camino::Utf8PathBuf> {
+    mappings
+        .iter()
+        .filter_map(|mapping| {
+            let relative = path.strip_prefix(&mapping.container).ok()?;
+            Some((
+                mapping.container.components().count(),
+                mapping.host.join(relative),
+            ))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, host_path)| host_path)
+}
+
+/// `contract_source_workdir` on docker daemon's host as per `path_mapping`;
+/// explicit mappings are tried first; `contract_source_workdir` is returned as is
+/// if no mappings are requested, and it's an error if they are, but none matches, as docker
+/// would bind mount an empty directory, created in place of the missing host path
+pub(crate) fn resolve(
+    path_mapping: &PathMapping,
+    contract_source_workdir: &camino::Utf8Path,
+    reporter: &dyn Reporter,
+) -> eyre::Result<camino::Utf8PathBuf> {
+    if path_mapping.mappings.is_empty() && !path_mapping.detect {
+        return Ok(contract_source_workdir.to_path_buf());
+    }
+    // mount points are canonical paths
+    let workdir = dunce::canonicalize(contract_source_workdir)
+        .ok()
+        .and_then(|path| camino::Utf8PathBuf::from_path_buf(path).ok())
+        .unwrap_or_else(|| contract_source_workdir.to_path_buf());
+
+    let mut host_path = translate(&workdir, &path_mapping.mappings);
+    if host_path.is_none() && path_mapping.detect {
+        let detected = detect(&path_mapping.filesystems)?;
+        tracing::debug!("detected path mappings: {:#?}", detected);
+        host_path = translate(&workdir, &detected);
+    }
+    match host_path {
+        Some(host_path) => {
+            reporter.report(
+                Message::new()
+                    .title("workdir on docker host:")
+                    .normal(host_path.as_str()),
+            );
+            Ok(host_path)
+        }
+        None => Err(eyre::eyre!(
+            "no path mapping matches `{}`, its location on docker host is unknown; \
+            consider an explicit mapping of one of its parent directories",
+            workdir
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilesystemMount, Mapping, PathMapping};
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mountinfo = "\
+1193 1026 0:120 / / rw,relatime master:466 - overlay overlay rw,lowerdir=/var/lib/docker/overlay2/l/A
+1194 1193 0:123 / /proc rw,nosuid,nodev,noexec,relatime - proc proc rw
+1211 1193 259:2 /var/lib/docker/containers/abc/hosts /etc/hosts rw,relatime - ext4 /dev/nvme0n1p2 rw
+1212 1193 259:2 /home/user/my\\040contracts /work rw,relatime - ext4 /dev/nvme0n1p2 rw
+1213 1193 8:17 /contracts /data rw,relatime shared:7 - ext4 /dev/sdb1 rw
+";
+        assert_eq!(
+            super::parse_mountinfo(mountinfo, &[]),
+            vec![
+                Mapping {
+                    container: "/etc/hosts".into(),
+                    host: "/var/lib/docker/containers/abc/hosts".into(),
+                },
+                Mapping {
+                    container: "/work".into(),
+                    host: "/home/user/my contracts".into(),
+                },
+                Mapping {
+                    container: "/data".into(),
+                    host: "/contracts".into(),
+                },
+            ]
+        );
+
+        // `/dev/sdb1` is mounted at `/mnt/disk` on the host
+        let filesystems = [FilesystemMount {
+            source: "/dev/sdb1".to_string(),
+            host: "/mnt/disk".into(),
+        }];
+        let mappings = super::parse_mountinfo(mountinfo, &filesystems);
+        assert_eq!(
+            mappings.last(),
+            Some(&Mapping {
+                container: "/data".into(),
+                host: "/mnt/disk/contracts".into(),
+            })
+        );
+        assert_eq!(mappings[1].host, "/home/user/my contracts");
+    }
+
+    #[test]
+    fn test_resolve() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let workdir = camino::Utf8PathBuf::from_path_buf(dunce::canonicalize(tempdir.path())?)
+            .expect("utf-8 path");
+        let reporter = crate::reporter::Silent;
+
+        // no mappings requested
+        assert_eq!(
+            super::resolve(&Default::default(), &workdir, &reporter)?,
+            workdir
+        );
+
+        let parent = workdir.parent().expect("parent of tempdir");
+        let path_mapping = PathMapping {
+            mappings: vec![Mapping {
+                container: parent.to_path_buf(),
+                host: "/host/tmp".into(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            super::resolve(&path_mapping, &workdir, &reporter)?,
+            camino::Utf8Path::new("/host/tmp").join(workdir.file_name().expect("file name"))
+        );
+
+        let path_mapping = PathMapping {
+            mappings: vec![Mapping {
+                container: "/nonexistent/verifier".into(),
+                host: "/host".into(),
+            }],
+            ..Default::default()
+        };
+        let err = super::resolve(&path_mapping, &workdir, &reporter).unwrap_err();
+        assert!(err.to_string().contains("no path mapping matches"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate() {
+        let mappings = vec![
+            Mapping {
+                container: "/work".into(),
+                host: "/home/user/work".into(),
+            },
+            Mapping {
+                container: "/work/cache".into(),
+                host: "/var/cache/verifier".into(),
+            },
+        ];
+        assert_eq!(
+            super::translate("/work/repo".into(), &mappings),
+            Some("/home/user/work/repo".into())
+        );
+        assert_eq!(
+            super::translate("/work/cache/repo".into(), &mappings),
+            Some("/var/cache/verifier/repo".into())
+        );
+        assert_eq!(super::translate("/workspace/repo".into(), &mappings), None);
+    }
+}
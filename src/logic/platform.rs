@@ -0,0 +1,255 @@
+//! Platform of a build environment's container.
+//!
+//! Images of `sourcescan/cargo-near` are built for `linux/amd64`; on `arm64` machines such
+//! a container is run under emulation, which is slow and may fail, so the platform is passed
+//! explicitly to `docker image pull` and `docker run` and emulation is reported.
+use serde::{Deserialize, Serialize};
+
+use crate::logic::nep330_build::Backend;
+use crate::reporter::{Message, Reporter};
+
+/// `os/architecture[/variant]`, e.g. `linux/amd64` or `linux/arm64/v8`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Platform {
+    pub os: String,
+    pub architecture: String,
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    pub fn linux_amd64() -> Self {
+        Self {
+            os: "linux".to_string(),
+            architecture: "amd64".to_string(),
+            variant: None,
+        }
+    }
+
+    /// `linux/<arch>` of the machine the verifier runs on, in terms of `GOARCH`,
+    /// which is what `docker` uses for platforms
+    pub fn host() -> Self {
+        Self {
+            os: "linux".to_string(),
+            architecture: goarch(std::env::consts::ARCH, cfg!(target_endian = "little"))
+                .to_string(),
+            variant: None,
+        }
+    }
+
+    /// `allowed` without a variant matches any variant
+    pub(crate) fn is_allowed_by(&self, allowed: &Platform) -> bool {
+        self.os == allowed.os
+            && self.architecture == allowed.architecture
+            && (allowed.variant.is_none() || allowed.variant == self.variant)
+    }
+}
+
+impl std::str::FromStr for Platform {
+    type Err = eyre::Report;
+
+    fn from_str(platform: &str) -> Result<Self, Self::Err> {
+        let mut parts = platform.split('/');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(os), Some(architecture), variant, None)
+                if !os.is_empty()
+                    && !architecture.is_empty()
+                    && variant.is_none_or(|variant| !variant.is_empty()) =>
+            {
+                Ok(Self {
+                    os: os.to_string(),
+                    architecture: architecture.to_string(),
+                    variant: variant.map(str::to_string),
+                })
+            }
+            _ => Err(eyre::eyre!(
+                "`{}` isn't a valid `os/architecture[/variant]` platform",
+                platform
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(ref variant) = self.variant {
+            write!(f, "/{}", variant)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Platform {
+    type Error = eyre::Report;
+
+    fn try_from(platform: String) -> Result<Self, Self::Error> {
+        platform.parse()
+    }
+}
+
+impl From<Platform> for String {
+    fn from(platform: Platform) -> Self {
+        platform.to_string()
+    }
+}
+
+/// `GOARCH` of rust's `std::env::consts::ARCH`, which doesn't tell endianness apart
+fn goarch(arch: &str, little_endian: bool) -> &str {
+    match arch {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        "powerpc64" if little_endian => "ppc64le",
+        "powerpc64" => "ppc64",
+        "loongarch64" => "loong64",
+        arch => arch,
+    }
+}
+
+/// `os/arch` of docker daemon, which differs from [Platform::host] for a remote `DOCKER_HOST`;
+/// [Platform::host] if daemon cannot be queried
+pub fn daemon(backend: &Backend) -> Platform {
+    match daemon_on(backend) {
+        Ok(platform) => platform,
+        Err(err) => {
+            tracing::debug!(
+                "docker daemon platform is assumed to be host one: {:?}",
+                err
+            );
+            Platform::host()
+        }
+    }
+}
+
+fn daemon_on(backend: &Backend) -> eyre::Result<Platform> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        let version = client.version()?;
+        return match (version["Os"].as_str(), version["Arch"].as_str()) {
+            (Some(os), Some(arch)) => format!("{}/{}", os, arch).parse(),
+            _ => Err(eyre::eyre!("`Os` or `Arch` missing in response of version")),
+        };
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.args(["version", "--format", "{{.Server.Os}}/{{.Server.Arch}}"]);
+    let output = docker_cmd
+        .output()
+        .map_err(|err| eyre::eyre!("failed to execute `{:?}`: {:?}", docker_cmd, err))?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`{:?}` failed with {}: {}",
+            docker_cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse()
+}
+
+fn from_image_on(docker_image: &str, backend: &Backend) -> eyre::Result<Platform> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        let inspect = client.inspect_image(docker_image)?;
+        let inspected: crate::logic::docker_checks::inspect::InspectedImage =
+            serde_json::from_value(inspect)?;
+        return Ok(inspected.platform());
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+    Ok(crate::logic::docker_checks::inspect::inspect(docker_image)?.platform())
+}
+
+/// `true` if a container of `build` platform is emulated on `daemon`;
+/// variants aren't compared, as e.g. `arm64/v8` runs natively on `arm64`
+pub fn is_emulated(build: &Platform, daemon: &Platform) -> bool {
+    build.os != daemon.os || build.architecture != daemon.architecture
+}
+
+/// `explicit` platform if it's set, platform of local `docker_image` otherwise;
+/// reports it along with platform of docker daemon, warning if the build is emulated.
+/// [None] if platform of the image cannot be determined, so that `docker` picks one
+pub(crate) fn resolve(
+    explicit: Option<Platform>,
+    docker_image: &str,
+    backend: &Backend,
+    reporter: &dyn Reporter,
+) -> Option<Platform> {
+    let platform = match explicit {
+        Some(platform) => platform,
+        None => match from_image_on(docker_image, backend) {
+            Ok(platform) => platform,
+            Err(err) => {
+                reporter.report(
+                    Message::new().warning("cannot inspect image for its platform, not pinning it"),
+                );
+                tracing::debug!("{:?}", err);
+                return None;
+            }
+        },
+    };
+    let daemon = daemon(backend);
+    reporter.report(
+        Message::new()
+            .title("build platform:")
+            .normal(platform.to_string())
+            .title("docker host platform:")
+            .normal(daemon.to_string()),
+    );
+    if is_emulated(&platform, &daemon) {
+        reporter.report(Message::new().warning(format!(
+            "`{}` container is emulated on `{}` docker host, which is slow and may fail; \
+            a wasm hash mismatch may be caused by emulation, consider verifying on a `{}` host",
+            platform, daemon, platform
+        )));
+    }
+    Some(platform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Platform;
+
+    #[test]
+    fn test_platform() -> eyre::Result<()> {
+        let platform: Platform = "linux/arm64/v8".parse()?;
+        assert_eq!(platform.variant.as_deref(), Some("v8"));
+        assert_eq!(platform.to_string(), "linux/arm64/v8");
+        assert!("linux".parse::<Platform>().is_err());
+        assert!("linux/amd64/".parse::<Platform>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_goarch() {
+        assert_eq!(super::goarch("x86_64", true), "amd64");
+        assert_eq!(super::goarch("aarch64", true), "arm64");
+        assert_eq!(super::goarch("powerpc64", true), "ppc64le");
+        assert_eq!(super::goarch("powerpc64", false), "ppc64");
+        assert_eq!(super::goarch("s390x", false), "s390x");
+    }
+
+    #[test]
+    fn test_is_emulated() -> eyre::Result<()> {
+        let amd64 = Platform::linux_amd64();
+        assert!(!super::is_emulated(&amd64, &"linux/amd64".parse()?));
+        assert!(super::is_emulated(&amd64, &"linux/arm64".parse()?));
+        assert!(!super::is_emulated(
+            &"linux/arm64/v8".parse()?,
+            &"linux/arm64".parse()?
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde() -> eyre::Result<()> {
+        let platform: Platform = serde_json::from_str("\"linux/arm64/v8\"")?;
+        assert_eq!(platform.architecture, "arm64");
+        assert_eq!(serde_json::to_string(&platform)?, "\"linux/arm64/v8\"");
+        assert!(serde_json::from_str::<Platform>("\"amd64\"").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! Mount point of the source repository inside of a build environment's container.
+//!
+//! It's [NEP330_REPO_MOUNT] for `sourcescan/cargo-near` images, but images of other toolchains
+//! may use a different home layout. It can be declared in
+//! [WhitelistEntry::repo_mount](crate::types::whitelist::WhitelistEntry::repo_mount)
+//! or with [LABEL] on the image itself.
+use std::str::FromStr;
+
+use crate::logic::NEP330_REPO_MOUNT;
+use crate::logic::nep330_build::Backend;
+use crate::reporter::{Message, Reporter};
+
+/// image label, declaring the repository mount point of a build environment
+pub const LABEL: &str = "org.near.nep330.repo-mount";
+
+pub fn validate(repo_mount: &str) -> eyre::Result<()> {
+    let path = unix_path::PathBuf::from_str(repo_mount).map_err(|err| {
+        eyre::eyre!(
+            "repository mount point `{}` isn't a valid unix path: {:#?}",
+            repo_mount,
+            err
+        )
+    })?;
+    if !path.is_absolute() || path.parent().is_none() {
+        return Err(eyre::eyre!(
+            "repository mount point `{}` has to be an absolute unix path other than `/`",
+            repo_mount
+        ));
+    }
+    if path
+        .components()
+        .any(|component| component == unix_path::Component::ParentDir)
+    {
+        return Err(eyre::eyre!(
+            "repository mount point `{}` cannot contain `..`",
+            repo_mount
+        ));
+    }
+    Ok(())
+}
+
+/// value of [LABEL] on a local `docker_image`, if any
+pub fn from_image_label(docker_image: &str) -> eyre::Result<Option<String>> {
+    let format = format!("{{{{ index .Config.Labels \"{}\" }}}}", LABEL);
+    let mut docker_cmd = std::process::Command::new("docker");
+    docker_cmd.args(["image", "inspect", "--format", &format, docker_image]);
+    let output = docker_cmd
+        .output()
+        .map_err(|err| eyre::eyre!("failed to execute `{:?}`: {:?}", docker_cmd, err))?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "`{:?}` failed with {}: {}",
+            docker_cmd,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let label = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    // absent label is rendered by `docker` as `<no value>` or an empty string
+    if label.is_empty() || label == "<no value>" {
+        return Ok(None);
+    }
+    Ok(Some(label))
+}
+
+fn from_image_label_on(docker_image: &str, backend: &Backend) -> eyre::Result<Option<String>> {
+    #[cfg(unix)]
+    if let Some(client) = backend.engine_client()? {
+        return client.image_label(docker_image, LABEL);
+    }
+    #[cfg(not(unix))]
+    backend.engine_client()?;
+    from_image_label(docker_image)
+}
+
+/// `explicit` mount point if it's set, [LABEL] of `docker_image` otherwise,
+/// falling back to [NEP330_REPO_MOUNT] if the image has no such label;
+/// `docker_image` has to be present locally, a failure to inspect it is an error, as the mount
+/// point, which depends on the local image cache, would make the build irreproducible
+pub(crate) fn resolve(
+    explicit: Option<String>,
+    docker_image: &str,
+    backend: &Backend,
+    reporter: &dyn Reporter,
+) -> eyre::Result<String> {
+    let repo_mount = match explicit {
+        Some(repo_mount) => repo_mount,
+        None => match from_image_label_on(docker_image, backend).map_err(|err| {
+            err.wrap_err(format!(
+                "cannot inspect image `{}` for `{}` label of its repository mount point",
+                docker_image, LABEL
+            ))
+        })? {
+            Some(repo_mount) => {
+                reporter.report(
+                    Message::new()
+                        .title("repository mount point of image label:")
+                        .normal(repo_mount.as_str()),
+                );
+                repo_mount
+            }
+            None => NEP330_REPO_MOUNT.to_string(),
+        },
+    };
+    validate(&repo_mount)?;
+    Ok(repo_mount)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_validate() {
+        assert!(super::validate("/home/near/code").is_ok());
+        assert!(super::validate("/workspace").is_ok());
+        assert!(super::validate("/").is_err());
+        assert!(super::validate("home/near/code").is_err());
+        assert!(super::validate("/home/../etc").is_err());
+    }
+
+    #[test]
+    fn test_resolve() -> eyre::Result<()> {
+        let backend = crate::logic::nep330_build::Backend::Cli;
+        let reporter = crate::reporter::Silent;
+        assert_eq!(
+            super::resolve(
+                Some("/workspace".to_string()),
+                "absent",
+                &backend,
+                &reporter
+            )?,
+            "/workspace"
+        );
+        assert!(super::resolve(Some("/".to_string()), "absent", &backend, &reporter).is_err());
+        // no fallback to the default mount point for an image, which cannot be inspected
+        let err = super::resolve(
+            None,
+            "near-verify-rs/absent-image:0.0.0",
+            &backend,
+            &reporter,
+        )
+        .unwrap_err();
+        assert!(format!("{:?}", err).contains("cannot inspect image"));
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! [tracing] spans of verification pipeline:
+//!
+//! `verify` → `checkout` → `pull` (or `load`) → `labels` → `signature` → `container_run` → `output_resolution` → `hash`
+//!
+//! `verify` and `checkout` spans are entered by the caller, as these steps are performed
+//! outside of this crate, and so is their `duration_ms` recorded with [record_duration];
+//! the rest are entered by this crate's functions and nest under whatever span is current at the time.
+//! Fields, which are only known on completion of a step (`exit_code`, `duration_ms`, `checksum_*`),
+//! are recorded into the spans before they're closed.
+use crate::types::contract_source_metadata::ContractSourceMetadata;
+use crate::types::image_reference::ImageReference;
+use crate::types::source_id::{GitReference, SourceId, SourceKind};
+
+/// splits `build_environment` into normalized `registry/repository[:tag]` and `<algorithm>:<digest>`
+/// parts for span fields
+fn image_and_digest(build_environment: &str) -> (String, Option<String>) {
+    match build_environment.parse::<ImageReference>() {
+        Ok(image) => (image.name_and_tag(), image.digest),
+        Err(_) => (build_environment.to_string(), None),
+    }
+}
+
+fn canonical_repo_and_rev(source_code_snapshot: &str) -> (Option<String>, Option<String>) {
+    match SourceId::from_url(source_code_snapshot) {
+        Ok(source_id) => {
+            let SourceKind::Git(GitReference::Rev(rev)) = source_id.kind();
+            (
+                Some(source_id.canonical_url().to_string()),
+                Some(rev.clone()),
+            )
+        }
+        Err(_) => (None, None),
+    }
+}
+
+/// root span of a single verification, carrying `canonical_repo`, `rev`, `contract_path`,
+/// `image` and `digest` fields, and `duration_ms` once recorded by the caller
+pub fn verify(contract_source_metadata: &ContractSourceMetadata) -> tracing::Span {
+    let span = tracing::info_span!(
+        "verify",
+        canonical_repo = tracing::field::Empty,
+        rev = tracing::field::Empty,
+        contract_path = tracing::field::Empty,
+        image = tracing::field::Empty,
+        digest = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    );
+    if let Some(ref build_info) = contract_source_metadata.build_info {
+        let (canonical_repo, rev) = canonical_repo_and_rev(&build_info.source_code_snapshot);
+        let (image, digest) = image_and_digest(&build_info.build_environment);
+        span.record("canonical_repo", canonical_repo);
+        span.record("rev", rev);
+        span.record("contract_path", build_info.contract_path.as_str());
+        span.record("image", image);
+        span.record("digest", digest);
+    }
+    span
+}
+
+/// span of a source code checkout, carrying `canonical_repo` and `rev` fields,
+/// and `duration_ms` once recorded by the caller
+pub fn checkout(source_id: &SourceId) -> tracing::Span {
+    let SourceKind::Git(GitReference::Rev(rev)) = source_id.kind();
+    tracing::info_span!(
+        "checkout",
+        canonical_repo = %source_id.canonical_url(),
+        rev = rev.as_str(),
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+pub(crate) fn pull(docker_image: &str) -> tracing::Span {
+    let (image, digest) = image_and_digest(docker_image);
+    tracing::info_span!(
+        "pull",
+        image,
+        digest,
+        mirrored_image = tracing::field::Empty,
+        pull_policy = tracing::field::Empty,
+        image_id = tracing::field::Empty,
+        platform = tracing::field::Empty,
+        exit_code = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+pub(crate) fn load(archive: &std::path::Path, docker_image: &str) -> tracing::Span {
+    let (image, digest) = image_and_digest(docker_image);
+    tracing::info_span!(
+        "load",
+        archive = %archive.display(),
+        image,
+        digest,
+        image_id = tracing::field::Empty,
+        exit_code = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+/// span of a check of image labels, carrying all `labels` of the image as a JSON object,
+/// and its `source` and `revision`, see [crate::logic::docker_checks::labels]
+pub(crate) fn labels(docker_image: &str) -> tracing::Span {
+    let (image, digest) = image_and_digest(docker_image);
+    tracing::info_span!(
+        "labels",
+        image,
+        digest,
+        source = tracing::field::Empty,
+        revision = tracing::field::Empty,
+        labels = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+#[cfg(feature = "signature")]
+pub(crate) fn signature(docker_image: &str) -> tracing::Span {
+    let (image, digest) = image_and_digest(docker_image);
+    tracing::info_span!(
+        "signature",
+        image,
+        digest,
+        signers = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+pub(crate) fn container_run(build_environment: &str, contract_path: &str) -> tracing::Span {
+    let (image, digest) = image_and_digest(build_environment);
+    tracing::info_span!(
+        "container_run",
+        image,
+        digest,
+        contract_path,
+        mirrored_image = tracing::field::Empty,
+        image_id = tracing::field::Empty,
+        platform = tracing::field::Empty,
+        userns = tracing::field::Empty,
+        container_name = tracing::field::Empty,
+        exit_code = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+pub(crate) fn output_resolution(mode: &'static str) -> tracing::Span {
+    tracing::info_span!(
+        "output_resolution",
+        mode,
+        wasm_path = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+pub(crate) fn hash(path: &camino::Utf8Path) -> tracing::Span {
+    tracing::info_span!(
+        "hash",
+        path = path.as_str(),
+        checksum_hex = tracing::field::Empty,
+        checksum_base58 = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
+
+/// records `duration_ms` field of a span, which has one, as time elapsed since `started`
+pub fn record_duration(span: &tracing::Span, started: std::time::Instant) {
+    span.record("duration_ms", started.elapsed().as_millis() as u64);
+}
+
+pub(crate) fn record_exit_code(span: &tracing::Span, status: &std::process::ExitStatus) {
+    if let Some(code) = status.code() {
+        span.record("exit_code", code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_image_and_digest() {
+        let (image, digest) = super::image_and_digest(
+            "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2",
+        );
+        assert_eq!(image, "docker.io/sourcescan/cargo-near:0.13.4-rust-1.85.0");
+        assert_eq!(
+            digest.as_deref(),
+            Some("sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2")
+        );
+
+        let (image, digest) = super::image_and_digest("hello-world");
+        assert_eq!(image, "docker.io/library/hello-world");
+        assert_eq!(digest, None);
+    }
+}
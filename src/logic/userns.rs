@@ -0,0 +1,195 @@
+//! User namespace strategy of a [Transfer::BindMount](crate::logic::nep330_build::Transfer::BindMount)
+//! build container, so that files written to the mounted source, including output wasm,
+//! are owned by (or at least readable by) the calling user.
+//!
+//! Passing `-u $(id -u):$(id -g)` is only right for rootful docker: under rootless docker
+//! the container's root is the calling user on the host, and the calling uid is mapped to a
+//! subordinate uid; under rootless podman the same holds unless `--userns=keep-id` is used.
+use crate::logic::docker_checks::environment::{DockerEnvironment, Runtime};
+use crate::logic::nep330_build::Backend;
+use crate::reporter::{Message, Reporter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// `-u uid:gid` of the calling user, which is the same uid on the host for rootful daemons
+    UidPassthrough,
+    /// `--userns=keep-id -u uid:gid`, podman maps the calling user to the same uid in container
+    KeepId,
+    /// `-u 0:0`, root of the container's user namespace is the calling user on the host
+    RootInUserns,
+}
+
+impl Strategy {
+    /// strategy, suitable for daemon of `environment`
+    pub fn select(environment: &DockerEnvironment) -> Self {
+        match (environment.rootless, environment.runtime) {
+            (false, _) => Self::UidPassthrough,
+            (true, Runtime::Podman) => Self::KeepId,
+            (true, Runtime::Docker) => Self::RootInUserns,
+        }
+    }
+
+    /// `docker run -u` argument, given `uid:gid` of the calling user
+    pub fn user(&self, uid_gid: String) -> String {
+        match self {
+            Self::UidPassthrough | Self::KeepId => uid_gid,
+            Self::RootInUserns => "0:0".to_string(),
+        }
+    }
+
+    /// `docker run --userns` argument, which is `HostConfig.UsernsMode` of docker engine API
+    pub fn userns_mode(&self) -> Option<&'static str> {
+        match self {
+            Self::KeepId => Some("keep-id"),
+            Self::UidPassthrough | Self::RootInUserns => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::UidPassthrough => "uid-passthrough",
+            Self::KeepId => "keep-id",
+            Self::RootInUserns => "root-in-userns",
+        }
+    }
+}
+
+/// `explicit` strategy, or the one [Strategy::select]-ed for daemon of `backend`;
+/// if the daemon cannot be queried, [Strategy::UidPassthrough] is assumed with a warning
+pub(crate) fn resolve(
+    explicit: Option<Strategy>,
+    backend: &Backend,
+    reporter: &dyn Reporter,
+) -> Strategy {
+    let strategy = match explicit {
+        Some(strategy) => strategy,
+        None => match crate::logic::docker_checks::environment::query(backend) {
+            Ok(environment) => Strategy::select(&environment),
+            Err(err) => {
+                reporter.report(Message::new().warning(format!(
+                    "rootless mode of docker daemon isn't detected, assuming `{}` \
+                    user namespace strategy: {:#}",
+                    Strategy::UidPassthrough.as_str(),
+                    err
+                )));
+                Strategy::UidPassthrough
+            }
+        },
+    };
+    if strategy != Strategy::UidPassthrough {
+        reporter.report(
+            Message::new()
+                .title("container user namespace:")
+                .normal(strategy.as_str()),
+        );
+    }
+    strategy
+}
+
+/// checks that output wasm of a build can be read by the calling user
+pub(crate) fn check_readable(
+    wasm_path: &camino::Utf8Path,
+    strategy: Strategy,
+    reporter: &dyn Reporter,
+) -> eyre::Result<()> {
+    if let Err(err) = std::fs::File::open(wasm_path) {
+        reporter.report(Message::new().hint(format!(
+            "output wasm was written with `{}` user namespace strategy, \
+            which may not match the rootless mode of docker daemon; \
+            consider overriding it or the container user",
+            strategy.as_str()
+        )));
+        return Err(eyre::eyre!(
+            "output wasm `{}` isn't readable by the calling user: {}",
+            wasm_path,
+            err
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Strategy;
+    use crate::logic::docker_checks::environment::{DockerEnvironment, Runtime};
+    use crate::logic::nep330_build::Backend;
+    use crate::reporter::Silent;
+
+    #[test]
+    fn test_select() {
+        let environment = |runtime, rootless| DockerEnvironment {
+            runtime,
+            server_version: "".to_string(),
+            os_type: "linux".to_string(),
+            architecture: "x86_64".to_string(),
+            rootless,
+            cgroup_version: None,
+            storage_driver: "overlay2".to_string(),
+            selinux: false,
+            apparmor: false,
+            security_options: vec![],
+        };
+        for runtime in [Runtime::Docker, Runtime::Podman] {
+            let strategy = Strategy::select(&environment(runtime, false));
+            assert_eq!(strategy, Strategy::UidPassthrough);
+            assert_eq!(strategy.user("1000:1000".to_string()), "1000:1000");
+            assert_eq!(strategy.userns_mode(), None);
+        }
+
+        let strategy = Strategy::select(&environment(Runtime::Podman, true));
+        assert_eq!(strategy, Strategy::KeepId);
+        assert_eq!(strategy.user("1000:1000".to_string()), "1000:1000");
+        assert_eq!(strategy.userns_mode(), Some("keep-id"));
+
+        let strategy = Strategy::select(&environment(Runtime::Docker, true));
+        assert_eq!(strategy, Strategy::RootInUserns);
+        assert_eq!(strategy.user("1000:1000".to_string()), "0:0");
+        assert_eq!(strategy.userns_mode(), None);
+    }
+
+    #[test]
+    fn test_resolve_explicit() {
+        // an explicit strategy is taken as is, without querying the daemon
+        for strategy in [
+            Strategy::UidPassthrough,
+            Strategy::KeepId,
+            Strategy::RootInUserns,
+        ] {
+            assert_eq!(
+                super::resolve(Some(strategy), &Backend::Cli, &Silent),
+                strategy
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_readable() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let wasm_path = camino::Utf8PathBuf::from_path_buf(tempdir.path().join("contract.wasm"))
+            .expect("utf8 path");
+
+        let err = super::check_readable(&wasm_path, Strategy::RootInUserns, &Silent).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("isn't readable by the calling user")
+        );
+
+        std::fs::write(&wasm_path, b"\0asm")?;
+        super::check_readable(&wasm_path, Strategy::RootInUserns, &Silent)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&wasm_path, std::fs::Permissions::from_mode(0o000))?;
+            // root reads files regardless of their mode
+            if std::fs::File::open(&wasm_path).is_err() {
+                let err = super::check_readable(&wasm_path, Strategy::KeepId, &Silent).unwrap_err();
+                assert!(
+                    err.to_string()
+                        .contains("isn't readable by the calling user")
+                );
+            }
+        }
+        Ok(())
+    }
+}
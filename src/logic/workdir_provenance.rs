@@ -0,0 +1,173 @@
+//! Preflight, which checks that `contract_source_workdir` really holds the snapshot,
+//! named in `source_code_snapshot`, before it is trusted by [crate::logic::nep330_build::run]
+use eyre::WrapErr;
+
+use crate::logic::internal::git;
+use crate::types::contract_source_metadata::BuildInfo;
+use crate::types::source_id::{GitReference, SourceId, SourceKind};
+
+/// Expectations about `contract_source_workdir`, in addition to what can be derived from [BuildInfo]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expected {
+    /// id of git tree object of the snapshot's root
+    ///
+    /// required for workdirs without `.git`, checked against `HEAD^{tree}` otherwise if present
+    pub tree_hash: Option<String>,
+}
+
+/// - for a workdir, containing `.git`, checks that its `HEAD` equals `rev` from `source_code_snapshot`,
+///   that no tracked files are modified and that no untracked files exist outside of ignored paths
+/// - for a workdir without `.git`, checks that git tree hash of its contents equals [Expected::tree_hash];
+///   nested submodules' contents aren't supported in this mode, as they're recorded as links in a tree
+pub fn check(
+    build_info: &BuildInfo,
+    contract_source_workdir: &camino::Utf8Path,
+    expected: &Expected,
+) -> eyre::Result<()> {
+    let source_id = SourceId::from_url(&build_info.source_code_snapshot)?;
+    let SourceKind::Git(GitReference::Rev(rev)) = source_id.kind();
+
+    let actual_tree_hash = if contract_source_workdir.join(".git").exists() {
+        check_git_checkout(contract_source_workdir, rev)?;
+        git::command(contract_source_workdir, &["rev-parse", "HEAD^{tree}"])?
+    } else {
+        if expected.tree_hash.is_none() {
+            return Err(eyre::eyre!(
+                "`{}` has no `.git`, its provenance cannot be checked without an expected git tree hash",
+                contract_source_workdir
+            ));
+        }
+        git::tree_hash(contract_source_workdir).wrap_err_with(|| {
+            format!(
+                "failed to compute git tree hash of `{}`",
+                contract_source_workdir
+            )
+        })?
+    };
+
+    if let Some(ref expected_tree_hash) = expected.tree_hash {
+        if !actual_tree_hash.eq_ignore_ascii_case(expected_tree_hash) {
+            return Err(eyre::eyre!(
+                "git tree hash of `{}` is `{}`, while `{}` is expected",
+                contract_source_workdir,
+                actual_tree_hash,
+                expected_tree_hash
+            ));
+        }
+    }
+    tracing::info!(
+        target: "near_teach_me",
+        "workdir `{}` provenance confirmed: rev `{}`, tree `{}`",
+        contract_source_workdir, rev, actual_tree_hash
+    );
+    Ok(())
+}
+
+fn check_git_checkout(workdir: &camino::Utf8Path, rev: &str) -> eyre::Result<()> {
+    let head = git::head_commit(workdir)?;
+    if !head.eq_ignore_ascii_case(rev) {
+        return Err(eyre::eyre!(
+            "`HEAD` of `{}` is `{}`, while `source_code_snapshot` pins `{}`",
+            workdir,
+            head,
+            rev
+        ));
+    }
+    let (untracked, modified): (Vec<String>, Vec<String>) = git::status_entries(workdir)?
+        .into_iter()
+        .partition(|entry| entry.starts_with("??"));
+    if !modified.is_empty() {
+        return Err(eyre::eyre!(
+            "tracked files of `{}` are modified:\n{}",
+            workdir,
+            crate::pretty_print::indent_payload(&modified.join("\n"))
+        ));
+    }
+    if !untracked.is_empty() {
+        return Err(eyre::eyre!(
+            "untracked files, which aren't ignored, exist in `{}`:\n{}",
+            workdir,
+            crate::pretty_print::indent_payload(&untracked.join("\n"))
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::internal::git;
+    use crate::types::contract_source_metadata::BuildInfo;
+
+    use super::{Expected, check};
+
+    fn commit_fixture(dir: &camino::Utf8Path) -> eyre::Result<String> {
+        std::fs::create_dir_all(dir.join("src"))?;
+        std::fs::write(dir.join("Cargo.toml"), "[package]\n")?;
+        std::fs::write(dir.join("src").join("lib.rs"), "")?;
+        std::fs::write(dir.join(".gitignore"), "target\n")?;
+        git::command(dir, &["init", "--quiet"])?;
+        git::command(dir, &["add", "--all"])?;
+        git::command(
+            dir,
+            &[
+                "-c",
+                "user.name=near",
+                "-c",
+                "user.email=near@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                "fixture",
+            ],
+        )?;
+        git::head_commit(dir)
+    }
+
+    #[test]
+    fn test_check() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = camino::Utf8PathBuf::from_path_buf(tempdir.path().to_path_buf())
+            .map_err(|err| eyre::eyre!("{:?}", err))?;
+        let rev = commit_fixture(&dir)?;
+        let tree = git::command(&dir, &["rev-parse", "HEAD^{tree}"])?;
+        let build_info = BuildInfo {
+            source_code_snapshot: format!("git+https://github.com/org/repo?rev={}", rev),
+            ..Default::default()
+        };
+
+        check(&build_info, &dir, &Expected::default())?;
+
+        std::fs::create_dir_all(dir.join("target"))?;
+        std::fs::write(dir.join("target").join("ignored"), "")?;
+        check(&build_info, &dir, &Expected::default())?;
+
+        std::fs::write(dir.join("untracked"), "")?;
+        let err = check(&build_info, &dir, &Expected::default()).unwrap_err();
+        assert!(format!("{:?}", err).contains("untracked files"));
+        std::fs::remove_file(dir.join("untracked"))?;
+
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\n")?;
+        let err = check(&build_info, &dir, &Expected::default()).unwrap_err();
+        assert!(format!("{:?}", err).contains("are modified"));
+        std::fs::write(dir.join("Cargo.toml"), "[package]\n")?;
+
+        let other_rev = BuildInfo {
+            source_code_snapshot: format!("git+https://github.com/org/repo?rev={}", "0".repeat(40)),
+            ..Default::default()
+        };
+        let err = check(&other_rev, &dir, &Expected::default()).unwrap_err();
+        assert!(format!("{:?}", err).contains("while `source_code_snapshot` pins"));
+
+        std::fs::remove_dir_all(dir.join(".git"))?;
+        assert!(check(&build_info, &dir, &Expected::default()).is_err());
+        let expected = Expected {
+            tree_hash: Some(tree),
+        };
+        check(&build_info, &dir, &expected)?;
+
+        std::fs::write(dir.join("src").join("lib.rs"), "// modified")?;
+        let err = check(&build_info, &dir, &expected).unwrap_err();
+        assert!(format!("{:?}", err).contains("git tree hash of"));
+        Ok(())
+    }
+}
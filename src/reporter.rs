@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use colored::Colorize;
+use serde::Serialize;
+
+/// Semantic style of a [Segment], rendered as a color by [Human] reporter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Style {
+    Normal,
+    /// a label of a value, which follows it
+    Title,
+    Hint,
+    Link,
+    Warning,
+    Failure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Segment {
+    pub style: Style,
+    pub text: String,
+}
+
+/// A single line (possibly a multiline one) of user-facing output, composed of styled segments,
+/// which are separated by a space when rendered as text
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Message {
+    pub segments: Vec<Segment>,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn segment(mut self, style: Style, text: impl Into<String>) -> Self {
+        self.segments.push(Segment {
+            style,
+            text: text.into(),
+        });
+        self
+    }
+    pub fn normal(self, text: impl Into<String>) -> Self {
+        self.segment(Style::Normal, text)
+    }
+    pub fn title(self, text: impl Into<String>) -> Self {
+        self.segment(Style::Title, text)
+    }
+    pub fn hint(self, text: impl Into<String>) -> Self {
+        self.segment(Style::Hint, text)
+    }
+    pub fn link(self, text: impl Into<String>) -> Self {
+        self.segment(Style::Link, text)
+    }
+    pub fn warning(self, text: impl Into<String>) -> Self {
+        self.segment(Style::Warning, text)
+    }
+    pub fn failure(self, text: impl Into<String>) -> Self {
+        self.segment(Style::Failure, text)
+    }
+
+    /// text without any ANSI escapes
+    pub fn plain_text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn colored_text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment.style {
+                Style::Normal => segment.text.normal().to_string(),
+                Style::Title => segment.text.green().to_string(),
+                Style::Hint => segment.text.cyan().to_string(),
+                Style::Link => segment.text.magenta().to_string(),
+                Style::Warning => segment.text.yellow().to_string(),
+                Style::Failure => segment.text.red().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Where stdout/stderr of spawned `docker` processes go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildOutput {
+    Inherit,
+    /// both streams are redirected to stderr of the calling process, keeping its stdout clean
+    Stderr,
+    Suppress,
+}
+
+/// Sink of user-facing output of [crate::logic::nep330_build] and [crate::logic::docker_checks]
+pub trait Reporter: std::fmt::Debug + Send + Sync {
+    fn report(&self, message: Message);
+
+    /// an empty line, separating groups of messages
+    fn separator(&self) {}
+
+    fn child_output(&self) -> ChildOutput {
+        ChildOutput::Inherit
+    }
+}
+
+/// colored output to stdout
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Human;
+
+impl Reporter for Human {
+    fn report(&self, message: Message) {
+        println!("{}", message.colored_text());
+    }
+    fn separator(&self) {
+        println!();
+    }
+}
+
+/// output to stdout without ANSI escapes, e.g. for CI logs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+impl Reporter for Plain {
+    fn report(&self, message: Message) {
+        println!("{}", message.plain_text());
+    }
+    fn separator(&self) {
+        println!();
+    }
+}
+
+/// one JSON object per message to stdout: `{"text": "...", "segments": [{"style": "...", "text": "..."}]}`
+///
+/// output of `docker` itself is redirected to stderr
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonLines;
+
+impl Reporter for JsonLines {
+    fn report(&self, message: Message) {
+        #[derive(Serialize)]
+        struct Line<'a> {
+            text: String,
+            segments: &'a [Segment],
+        }
+        let line = Line {
+            text: message.plain_text(),
+            segments: &message.segments,
+        };
+        match serde_json::to_string(&line) {
+            Ok(line) => println!("{}", line),
+            Err(err) => tracing::error!("cannot serialize {:?}: {:?}", message, err),
+        }
+    }
+
+    fn child_output(&self) -> ChildOutput {
+        ChildOutput::Stderr
+    }
+}
+
+/// no output at all, including output of `docker` itself
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Silent;
+
+impl Reporter for Silent {
+    fn report(&self, _message: Message) {}
+
+    fn child_output(&self) -> ChildOutput {
+        ChildOutput::Suppress
+    }
+}
+
+/// [Silent] if `quiet`, [Human] otherwise; the mapping of legacy `quiet: bool` arguments
+pub fn from_quiet(quiet: bool) -> Arc<dyn Reporter> {
+    if quiet {
+        Arc::new(Silent)
+    } else {
+        Arc::new(Human)
+    }
+}
+
+pub(crate) fn configure_child(reporter: &dyn Reporter, command: &mut std::process::Command) {
+    match reporter.child_output() {
+        ChildOutput::Inherit => {}
+        ChildOutput::Stderr => {
+            command.stdout(std::io::stderr()).stderr(std::io::stderr());
+        }
+        ChildOutput::Suppress => {
+            command
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Message;
+
+    #[test]
+    fn test_plain_text() {
+        let message = Message::new()
+            .title("docker image to be used:")
+            .normal("sourcescan/cargo-near");
+        assert_eq!(
+            message.plain_text(),
+            "docker image to be used: sourcescan/cargo-near"
+        );
+        let json = serde_json::to_string(&message).expect("no error");
+        assert_eq!(
+            json,
+            r#"{"segments":[{"style":"title","text":"docker image to be used:"},{"style":"normal","text":"sourcescan/cargo-near"}]}"#
+        );
+    }
+}
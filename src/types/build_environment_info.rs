@@ -0,0 +1,273 @@
+//! Toolchain versions, encoded in tags of `sourcescan/cargo-near` images,
+//! e.g. `0.13.4-rust-1.85.0`
+use std::sync::LazyLock;
+
+use cargo_metadata::semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::types::image_reference::ImageReference;
+
+static TAG: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r"^(?P<cargo_near>\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+?)?)-rust-(?P<rustc>\d+\.\d+(?:\.\d+)?)$",
+    )
+    .expect("no error")
+});
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildEnvironmentInfo {
+    pub image: ImageReference,
+    /// [None] if tag of [Self::image] doesn't follow `<cargo-near>-rust-<rustc>` convention
+    pub cargo_near: Option<Version>,
+    /// [None] if tag of [Self::image] doesn't follow `<cargo-near>-rust-<rustc>` convention
+    pub rustc: Option<Version>,
+}
+
+impl From<ImageReference> for BuildEnvironmentInfo {
+    fn from(image: ImageReference) -> Self {
+        let captures = image.tag.as_deref().and_then(|tag| TAG.captures(tag));
+        let (cargo_near, rustc) = match captures {
+            Some(captures) => (
+                Version::parse(&captures["cargo_near"]).ok(),
+                parse_rust_version(&captures["rustc"]),
+            ),
+            None => (None, None),
+        };
+        Self {
+            image,
+            cargo_near,
+            rustc,
+        }
+    }
+}
+
+impl std::str::FromStr for BuildEnvironmentInfo {
+    type Err = eyre::Report;
+
+    fn from_str(build_environment: &str) -> Result<Self, Self::Err> {
+        Ok(build_environment.parse::<ImageReference>()?.into())
+    }
+}
+
+/// `1.85` is `1.85.0`
+fn parse_rust_version(version: &str) -> Option<Version> {
+    match version.matches('.').count() {
+        1 => Version::parse(&format!("{}.0", version)).ok(),
+        _ => Version::parse(version).ok(),
+    }
+}
+
+/// inclusive bounds of toolchain versions of [BuildEnvironmentInfo]
+#[derive(Debug, Clone, PartialEq, Default, Eq, Serialize, Deserialize)]
+pub struct ToolchainPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_cargo_near: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cargo_near: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rustc: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rustc: Option<Version>,
+}
+
+impl ToolchainPolicy {
+    /// [None] if `info` satisfies the policy, the violation otherwise
+    pub fn violation(&self, info: &BuildEnvironmentInfo) -> Option<String> {
+        let check = |name: &str,
+                     version: &Option<Version>,
+                     min: &Option<Version>,
+                     max: &Option<Version>| {
+            if min.is_none() && max.is_none() {
+                return None;
+            }
+            let Some(version) = version else {
+                return Some(format!(
+                    "{} version cannot be determined from tag of `{}`",
+                    name, info.image
+                ));
+            };
+            if let Some(min) = min {
+                if version < min {
+                    return Some(format!("{} {} is older than {}", name, version, min));
+                }
+            }
+            if let Some(max) = max {
+                if version > max {
+                    return Some(format!("{} {} is newer than {}", name, version, max));
+                }
+            }
+            None
+        };
+        check(
+            "cargo-near",
+            &info.cargo_near,
+            &self.min_cargo_near,
+            &self.max_cargo_near,
+        )
+        .or_else(|| check("rustc", &info.rustc, &self.min_rustc, &self.max_rustc))
+    }
+
+    pub fn check(&self, info: &BuildEnvironmentInfo) -> eyre::Result<()> {
+        match self.violation(info) {
+            Some(violation) => Err(eyre::eyre!("toolchain policy violated: {}", violation)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl BuildEnvironmentInfo {
+    /// channel of the nearest `rust-toolchain.toml` or `rust-toolchain` file, looked up
+    /// from `crate_dir` up to `workdir`, if it's a version other than [Self::rustc];
+    /// named channels (`stable`, `nightly-...`) are not compared
+    pub fn rust_toolchain_disagreement(
+        &self,
+        workdir: &camino::Utf8Path,
+        crate_dir: &camino::Utf8Path,
+    ) -> eyre::Result<Option<String>> {
+        let Some(ref rustc) = self.rustc else {
+            return Ok(None);
+        };
+        let Some((path, channel)) = find_rust_toolchain_channel(workdir, crate_dir)? else {
+            return Ok(None);
+        };
+        let Some(channel_version) = parse_rust_version(&channel) else {
+            return Ok(None);
+        };
+        // `1.85` channel means latest patch of `1.85`
+        let agrees = match channel.matches('.').count() {
+            1 => channel_version.major == rustc.major && channel_version.minor == rustc.minor,
+            _ => channel_version == *rustc,
+        };
+        if agrees {
+            return Ok(None);
+        }
+        Ok(Some(format!(
+            "`{}` pins rust `{}`, which disagrees with rust `{}` of image `{}`",
+            path,
+            channel,
+            rustc,
+            self.image.familiar_name()
+        )))
+    }
+}
+
+fn find_rust_toolchain_channel(
+    workdir: &camino::Utf8Path,
+    crate_dir: &camino::Utf8Path,
+) -> eyre::Result<Option<(camino::Utf8PathBuf, String)>> {
+    #[derive(Deserialize)]
+    struct RustToolchainFile {
+        toolchain: Toolchain,
+    }
+    #[derive(Deserialize)]
+    struct Toolchain {
+        channel: Option<String>,
+    }
+
+    for dir in crate_dir.ancestors() {
+        if !dir.starts_with(workdir) {
+            break;
+        }
+        let toml_path = dir.join("rust-toolchain.toml");
+        let legacy_path = dir.join("rust-toolchain");
+        for path in [toml_path, legacy_path] {
+            if !path.is_file() {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)?;
+            let channel = match toml::from_str::<RustToolchainFile>(&contents) {
+                Ok(file) => file.toolchain.channel,
+                // legacy `rust-toolchain` may contain just a channel name
+                Err(_) if !contents.trim().contains('\n') => Some(contents.trim().to_string()),
+                Err(err) => {
+                    return Err(eyre::eyre!("failed to parse `{}`: {}", path, err));
+                }
+            };
+            return Ok(channel.map(|channel| (path, channel)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use cargo_metadata::semver::Version;
+
+    use super::{BuildEnvironmentInfo, ToolchainPolicy};
+
+    const IMAGE: &str = "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+
+    #[test]
+    fn test_parse() -> eyre::Result<()> {
+        let info: BuildEnvironmentInfo = IMAGE.parse()?;
+        assert_eq!(info.cargo_near, Some(Version::new(0, 13, 4)));
+        assert_eq!(info.rustc, Some(Version::new(1, 85, 0)));
+
+        let info: BuildEnvironmentInfo = "sourcescan/cargo-near:0.14.0-rc.1-rust-1.86".parse()?;
+        assert_eq!(info.cargo_near, Some(Version::parse("0.14.0-rc.1")?));
+        assert_eq!(info.rustc, Some(Version::new(1, 86, 0)));
+
+        let info: BuildEnvironmentInfo = "dj8yfo/sourcescan:0.x.x-dev-pr-262".parse()?;
+        assert_eq!(info.cargo_near, None);
+        assert_eq!(info.rustc, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy() -> eyre::Result<()> {
+        let info: BuildEnvironmentInfo = IMAGE.parse()?;
+        let policy = ToolchainPolicy {
+            min_cargo_near: Some(Version::new(0, 13, 0)),
+            max_rustc: Some(Version::new(1, 85, 0)),
+            ..Default::default()
+        };
+        assert_eq!(policy.violation(&info), None);
+
+        let policy = ToolchainPolicy {
+            min_rustc: Some(Version::new(1, 86, 0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.violation(&info).as_deref(),
+            Some("rustc 1.85.0 is older than 1.86.0")
+        );
+
+        let untagged: BuildEnvironmentInfo = "dj8yfo/sourcescan:0.x.x-dev-pr-262".parse()?;
+        assert!(policy.violation(&untagged).is_some());
+        assert_eq!(ToolchainPolicy::default().violation(&untagged), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rust_toolchain_disagreement() -> eyre::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let workdir = camino::Utf8PathBuf::from_path_buf(tempdir.path().to_path_buf())
+            .map_err(|err| eyre::eyre!("{:?}", err))?;
+        let crate_dir = workdir.join("contract");
+        std::fs::create_dir_all(&crate_dir)?;
+        let info: BuildEnvironmentInfo = IMAGE.parse()?;
+
+        assert_eq!(
+            info.rust_toolchain_disagreement(&workdir, &crate_dir)?,
+            None
+        );
+
+        std::fs::write(
+            workdir.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.85\"\n",
+        )?;
+        assert_eq!(
+            info.rust_toolchain_disagreement(&workdir, &crate_dir)?,
+            None
+        );
+
+        std::fs::write(crate_dir.join("rust-toolchain"), "1.84.1\n")?;
+        let disagreement = info.rust_toolchain_disagreement(&workdir, &crate_dir)?;
+        assert!(
+            disagreement
+                .expect("to be some")
+                .contains("pins rust `1.84.1`, which disagrees with rust `1.85.0`")
+        );
+        Ok(())
+    }
+}
@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::env_keys;
 
-mod validate;
+mod from_local_repo;
+pub(crate) mod validate;
 /// The struct provides information about deployed contract's source code and supported standards.
 ///
 /// Contract source metadata follows [**NEP-330 standard**](https://github.com/near/NEPs/blob/master/neps/nep-0330.md) for smart contracts
@@ -212,7 +213,8 @@ mod build_info {
         pub source_code_snapshot: String,
         /// A path within the build environment, where the result WASM binary has been put
         /// during build.
-        /// This should be a subpath of `/home/near/code`
+        /// This should be a subpath of the build environment's repository mount point,
+        /// `/home/near/code` by default (see [crate::logic::repo_mount])
         ///
         /// This field is an addition of **1.3.0** **NEP-330** revision
         ///
@@ -1,16 +1,50 @@
 use std::str::FromStr;
 
+use cargo_metadata::semver::Version;
+use chrono::{DateTime, Utc};
+
 use crate::{
     logic::NEP330_REPO_MOUNT,
-    types::whitelist::{Whitelist, WhitelistEntry},
+    types::{
+        build_environment_info::BuildEnvironmentInfo,
+        image_reference::ImageReference,
+        whitelist::{EntryKind, Whitelist, WhitelistEntry, WhitelistPolicy},
+    },
 };
 
 use super::BuildInfo;
-pub const DOCKER_IMAGE_REGEX_PATTERN: &str =
-    r#"^(?P<image>[^:@\s]+?)(?::(?P<tag>[^@\s]+?))?(@sha256:(?P<digest>[a-f0-9]{64}))$"#;
 
 impl super::ContractSourceMetadata {
-    pub fn validate(&self, whitelist: Option<Whitelist>) -> eyre::Result<()> {
+    /// returns the matched entry of `whitelist`, if one is supplied; otherwise any image, pinned
+    /// with a digest, is accepted as before ([WhitelistPolicy::AnyImage]), which doesn't protect
+    /// against an arbitrary build environment: opt into the curated whitelist with
+    /// [Self::validate_with] and [WhitelistPolicy::Curated];
+    /// time bounds of whitelist entries are evaluated at current time
+    pub fn validate(&self, whitelist: Option<Whitelist>) -> eyre::Result<Option<WhitelistEntry>> {
+        self.validate_at(whitelist, Utc::now())
+    }
+
+    /// [Self::validate] with time bounds of whitelist entries evaluated `at` a given time,
+    /// e.g. to replay a historical verification with the policy, which applied back then
+    pub fn validate_at(
+        &self,
+        whitelist: Option<Whitelist>,
+        at: DateTime<Utc>,
+    ) -> eyre::Result<Option<WhitelistEntry>> {
+        let policy = match whitelist {
+            Some(whitelist) => WhitelistPolicy::Custom(whitelist),
+            None => WhitelistPolicy::AnyImage,
+        };
+        self.validate_with(&policy, at)
+    }
+
+    /// validation against `policy`, [WhitelistPolicy::Curated] being the secure default;
+    /// returns the matched entry unless `policy` is [WhitelistPolicy::AnyImage]
+    pub fn validate_with(
+        &self,
+        policy: &WhitelistPolicy,
+        at: DateTime<Utc>,
+    ) -> eyre::Result<Option<WhitelistEntry>> {
         if self.build_info.is_none() {
             return Err(eyre::eyre!(
                 "`build_info` field of `ContractSourceMetadata` cannot be null"
@@ -23,53 +57,137 @@ impl super::ContractSourceMetadata {
         build_info.validate_build_command_basic()?;
 
         let image = build_info.validate_build_env_on_regex()?;
-        if let Some(whitelist) = whitelist {
-            let _entry = BuildInfo::validate_build_image_on_whitelist(&image, whitelist)?;
+        let entry = match policy.whitelist() {
+            Some(whitelist) => Some(BuildInfo::validate_build_image_on_whitelist(
+                &image,
+                whitelist,
+                at,
+                self.nep330_version()?.as_ref(),
+            )?),
+            None => None,
+        };
+
+        // mount point of an entry is its `repo_mount`, [NEP330_REPO_MOUNT] by default;
+        // without an entry it's declared by the image's [crate::logic::repo_mount::LABEL],
+        // so `output_wasm_path` is checked against it once the image is pulled
+        // by [crate::logic::nep330_build::run_opts]
+        match entry.as_ref() {
+            Some(entry) => {
+                let repo_mount = match entry.repo_mount.as_deref() {
+                    Some(repo_mount) => {
+                        crate::logic::repo_mount::validate(repo_mount)?;
+                        repo_mount
+                    }
+                    None => NEP330_REPO_MOUNT,
+                };
+                build_info.validate_output_wasm_path_on(repo_mount)?;
+            }
+            // only checked to be an absolute unix path here
+            None => build_info.validate_output_wasm_path_on("/")?,
         }
 
-        build_info.validate_output_wasm_path()?;
+        Ok(entry)
+    }
 
-        Ok(())
+    /// version of `nep330` standard of [Self::standards], if it's declared
+    pub fn nep330_version(&self) -> eyre::Result<Option<Version>> {
+        self.standards
+            .iter()
+            .find(|standard| standard.standard == "nep330")
+            .map(|standard| {
+                Version::parse(&standard.version).map_err(|err| {
+                    eyre::eyre!("invalid nep330 version `{}`: {}", standard.version, err)
+                })
+            })
+            .transpose()
     }
 }
 
 impl super::build_info::BuildInfo {
-    pub fn validate_build_env_on_regex(&self) -> eyre::Result<String> {
-        let regex = regex::Regex::new(DOCKER_IMAGE_REGEX_PATTERN).expect("no error");
-
-        if !regex.is_match(&self.build_environment) {
+    /// parses `build_environment`, which has to be pinned with a `sha256` digest
+    pub fn validate_build_env_on_regex(&self) -> eyre::Result<ImageReference> {
+        let image = ImageReference::from_str(&self.build_environment)?;
+        if image.sha256_digest().is_none() {
             return Err(eyre::eyre!(
-                "`{}` doesn't match {}",
+                "`{}` isn't pinned with a `@sha256:<digest>`",
                 self.build_environment,
-                DOCKER_IMAGE_REGEX_PATTERN
             ));
         }
-        let image = regex
-            .captures(&self.build_environment)
-            .and_then(|captures| captures.name("image"));
-
-        image
-            .map(|capture| capture.as_str().to_string())
-            .ok_or(eyre::eyre!(
-                "`{}` didn't match any `image` group in {}",
-                self.build_environment,
-                DOCKER_IMAGE_REGEX_PATTERN
-            ))
+        Ok(image)
     }
+    pub fn build_environment_info(&self) -> eyre::Result<BuildEnvironmentInfo> {
+        self.build_environment.parse()
+    }
+    /// returns the first active allow entry, matching `image` as per [WhitelistEntry::matches];
+    /// any matching active deny entry rejects `image`, overriding allow entries;
+    /// entries are active as per [WhitelistEntry::inactive_reason] and [WhitelistEntry::toolchain]
     pub fn validate_build_image_on_whitelist(
-        image: &str,
+        image: &ImageReference,
         whitelist: Whitelist,
+        at: DateTime<Utc>,
+        nep330_version: Option<&Version>,
     ) -> eyre::Result<WhitelistEntry> {
-        let entry_match = whitelist
-            .iter()
-            .find(|entry| entry.expected_docker_image == image)
-            .cloned();
-
-        entry_match.ok_or(eyre::eyre!(
-            "no matching entry found for `{}` in whitelist : {:?}",
-            image,
-            whitelist
-        ))
+        let mut allowed = None;
+        let mut inactive = vec![];
+        for (index, entry) in whitelist.iter().enumerate() {
+            if !entry.matches(image)? {
+                continue;
+            }
+            let reason = match entry.inactive_reason(at, nep330_version)? {
+                Some(reason) => Some(reason),
+                None => entry.toolchain.as_ref().and_then(|toolchain| {
+                    toolchain.violation(&BuildEnvironmentInfo::from(image.clone()))
+                }),
+            };
+            if let Some(reason) = reason {
+                tracing::debug!(
+                    "whitelist entry #{} {} is inactive: {}",
+                    index,
+                    entry,
+                    reason
+                );
+                inactive.push(format!("#{}: {} ({})", index, entry, reason));
+                continue;
+            }
+            match entry.kind {
+                EntryKind::Deny => {
+                    return Err(eyre::eyre!(
+                        "`{}` is denied by whitelist entry #{}: {}",
+                        image,
+                        index,
+                        entry
+                    ));
+                }
+                EntryKind::Allow => {
+                    if allowed.is_none() {
+                        allowed = Some((index, entry));
+                    }
+                }
+            }
+        }
+
+        match allowed {
+            Some((index, entry)) => {
+                tracing::debug!(
+                    "`{}` is allowed by whitelist entry #{}: {}",
+                    image,
+                    index,
+                    entry
+                );
+                Ok(entry.clone())
+            }
+            None if !inactive.is_empty() => Err(eyre::eyre!(
+                "`{}` only matches inactive whitelist entries at {}:\n{}",
+                image,
+                at.to_rfc3339(),
+                inactive.join("\n")
+            )),
+            None => Err(eyre::eyre!(
+                "no matching entry found for `{}` in whitelist : {:?}",
+                image.familiar_name(),
+                whitelist
+            )),
+        }
     }
     pub fn validate_contract_path(&self) -> eyre::Result<()> {
         match unix_path::PathBuf::from_str(&self.contract_path) {
@@ -117,6 +235,11 @@ impl super::build_info::BuildInfo {
     }
 
     pub fn validate_output_wasm_path(&self) -> eyre::Result<()> {
+        self.validate_output_wasm_path_on(NEP330_REPO_MOUNT)
+    }
+
+    /// checks that `output_wasm_path` is a subpath of `repo_mount` of the build environment
+    pub fn validate_output_wasm_path_on(&self, repo_mount: &str) -> eyre::Result<()> {
         if let Some(ref output_wasm_path) = self.output_wasm_path {
             match unix_path::PathBuf::from_str(output_wasm_path) {
                 Err(err) => {
@@ -134,11 +257,11 @@ impl super::build_info::BuildInfo {
                         ));
                     }
 
-                    if !path.starts_with(NEP330_REPO_MOUNT) {
+                    if !path.starts_with(repo_mount) {
                         return Err(eyre::eyre!(
                             "`output_wasm_path` field (`{}`) of `BuildInfo` isn't a subpath of `{}`",
                             output_wasm_path,
-                            NEP330_REPO_MOUNT,
+                            repo_mount,
                         ));
                     }
                 }
@@ -150,24 +273,149 @@ impl super::build_info::BuildInfo {
 
 #[cfg(test)]
 mod tests {
-    use crate::types::contract_source_metadata::validate::DOCKER_IMAGE_REGEX_PATTERN;
+    use cargo_metadata::semver::Version;
+    use chrono::Utc;
+
+    use crate::types::contract_source_metadata::{BuildInfo, ContractSourceMetadata};
+    use crate::types::image_reference::ImageReference;
+    use crate::types::whitelist::{EntryKind, WhitelistEntry, WhitelistPolicy};
 
     #[test]
     fn check_regex() {
-        let regex = regex::Regex::new(DOCKER_IMAGE_REGEX_PATTERN).expect("no error");
         let right_haystack = "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
 
-        assert!(regex.is_match(right_haystack));
-
-        let image = regex
-            .captures(right_haystack)
-            .and_then(|captures| captures.name("image"));
-        assert_eq!("sourcescan/cargo-near", image.expect("to be some").as_str());
+        let image = right_haystack
+            .parse::<ImageReference>()
+            .expect("to be valid");
+        assert_eq!("sourcescan/cargo-near", image.familiar_name());
 
         let wrong_haystack_a = " sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
-        assert!(!regex.is_match(wrong_haystack_a));
+        assert!(wrong_haystack_a.parse::<ImageReference>().is_err());
 
         let wrong_haystack_b = "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2 ";
-        assert!(!regex.is_match(wrong_haystack_b));
+        assert!(wrong_haystack_b.parse::<ImageReference>().is_err());
+    }
+
+    #[test]
+    fn test_whitelist_normalized_match() -> eyre::Result<()> {
+        let entry = |expected_docker_image: &str| WhitelistEntry {
+            expected_docker_image: expected_docker_image.to_string(),
+            ..Default::default()
+        };
+        let evaluate = |image, whitelist| {
+            BuildInfo::validate_build_image_on_whitelist(image, whitelist, Utc::now(), None)
+        };
+        let image = "docker.io/sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2".parse()?;
+
+        for expected in [
+            "sourcescan/cargo-near",
+            "index.docker.io/sourcescan/cargo-near",
+            "sourcescan/cargo-near:0.13.4-rust-1.85.0",
+        ] {
+            assert!(evaluate(&image, vec![entry(expected)]).is_ok());
+        }
+        for expected in [
+            "sourcescan/cargo-near:0.13.3-rust-1.84.0",
+            "ghcr.io/sourcescan/cargo-near",
+        ] {
+            assert!(evaluate(&image, vec![entry(expected)]).is_err());
+        }
+
+        let deny = WhitelistEntry {
+            kind: EntryKind::Deny,
+            ..entry(
+                "sourcescan/cargo-near@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2",
+            )
+        };
+        let err = evaluate(&image, vec![entry("sourcescan/cargo-near"), deny]).unwrap_err();
+        assert!(err.to_string().contains("denied by whitelist entry #1"));
+
+        let image = "localhost:5000/cargo-near:tag@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2".parse()?;
+        assert!(evaluate(&image, vec![entry("localhost:5000/cargo-near")]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_whitelist_time_bounds() -> eyre::Result<()> {
+        let image = "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2".parse()?;
+        let bounded = WhitelistEntry {
+            expected_docker_image: "sourcescan/cargo-near".to_string(),
+            valid_from: Some("2025-01-01T00:00:00Z".parse()?),
+            valid_until: Some("2025-06-01T00:00:00Z".parse()?),
+            min_nep330_version: Some("1.2.0".to_string()),
+            ..Default::default()
+        };
+        let v1_2_0 = Version::new(1, 2, 0);
+        let evaluate = |entry: &WhitelistEntry, at: &str, version: Option<&Version>| {
+            BuildInfo::validate_build_image_on_whitelist(
+                &image,
+                vec![entry.clone()],
+                at.parse().expect("valid timestamp"),
+                version,
+            )
+        };
+
+        assert!(evaluate(&bounded, "2025-03-01T00:00:00Z", Some(&v1_2_0)).is_ok());
+        let err = evaluate(&bounded, "2025-07-01T00:00:00Z", Some(&v1_2_0)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expired at 2025-06-01T00:00:00+00:00")
+        );
+        let err = evaluate(&bounded, "2024-12-31T00:00:00Z", Some(&v1_2_0)).unwrap_err();
+        assert!(err.to_string().contains("not valid until"));
+        let err = evaluate(
+            &bounded,
+            "2025-03-01T00:00:00Z",
+            Some(&Version::new(1, 1, 0)),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("requires nep330 1.2.0 or newer"));
+
+        let revoked = WhitelistEntry {
+            revoked: Some("produces non-deterministic wasm".to_string()),
+            ..bounded
+        };
+        let err = evaluate(&revoked, "2025-03-01T00:00:00Z", Some(&v1_2_0)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("revoked: produces non-deterministic wasm")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_without_whitelist() -> eyre::Result<()> {
+        let metadata = |build_environment: &str| -> eyre::Result<ContractSourceMetadata> {
+            Ok(serde_json::from_value(serde_json::json!({
+                "build_info": {
+                    "build_command": ["cargo", "near", "build", "non-reproducible-wasm", "--locked"],
+                    "build_environment": build_environment,
+                    "contract_path": "",
+                    "source_code_snapshot": "git+https://github.com/dj8yfo/verify_contracts_collection?rev=e3303f0cf8761b99f84f93c3a2d7046be6f4edb5"
+                },
+                "standards": [{"standard": "nep330", "version": "1.2.0"}],
+            }))?)
+        };
+
+        let curated = metadata(
+            "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2",
+        )?;
+        assert!(curated.validate(None)?.is_none());
+        assert!(
+            curated
+                .validate_with(&WhitelistPolicy::Curated, Utc::now())?
+                .is_some()
+        );
+
+        let nonstandard = metadata(
+            "dj8yfo/sourcescan:0.x.x-dev-pr-262@sha256:a231d4bf975d561a06dd5357f2ac03c883e8b3b510994f3b40c9b975dcdb02ce",
+        )?;
+        assert!(nonstandard.validate(None)?.is_none());
+        assert!(
+            nonstandard
+                .validate_with(&WhitelistPolicy::Curated, Utc::now())
+                .is_err()
+        );
+        Ok(())
     }
 }
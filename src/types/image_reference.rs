@@ -0,0 +1,257 @@
+//! Docker image reference, parsed according to the grammar of
+//! [distribution/reference](https://github.com/distribution/reference/blob/main/reference.go)
+//! and normalized the way `docker` CLI does it.
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+const LEGACY_DEFAULT_REGISTRY: &str = "index.docker.io";
+const OFFICIAL_REPOSITORY_PREFIX: &str = "library/";
+const NAME_TOTAL_LENGTH_MAX: usize = 255;
+
+static DOMAIN: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r"^(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9])(?:\.(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9]))*(?::[0-9]+)?$",
+    )
+    .expect("no error")
+});
+static PATH_COMPONENT: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^[a-z0-9]+(?:(?:[_.]|__|-+)[a-z0-9]+)*$").expect("no error")
+});
+static TAG: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.-]{0,127}$").expect("no error"));
+static DIGEST: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^[A-Za-z][A-Za-z0-9]*(?:[-_+.][A-Za-z][A-Za-z0-9]*)*:[0-9a-fA-F]{32,}$")
+        .expect("no error")
+});
+
+/// `[registry/]repository[:tag][@digest]`, e.g.
+/// `localhost:5000/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8...`
+///
+/// normalized: registry is [DEFAULT_REGISTRY] if it's omitted, and single component
+/// repositories of [DEFAULT_REGISTRY] get `library/` prefix, so that `sourcescan/cargo-near`
+/// and `docker.io/sourcescan/cargo-near` are equal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageReference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: Option<String>,
+    /// `<algorithm>:<hex>`, e.g. `sha256:a9d8...`
+    pub digest: Option<String>,
+}
+
+impl FromStr for ImageReference {
+    type Err = eyre::Report;
+
+    fn from_str(reference: &str) -> Result<Self, Self::Err> {
+        let err = |reason: &str| {
+            eyre::eyre!(
+                "`{}` isn't a valid docker image reference: {}",
+                reference,
+                reason
+            )
+        };
+
+        let (name_and_tag, digest) = match reference.split_once('@') {
+            Some((name_and_tag, digest)) => {
+                if !DIGEST.is_match(digest) {
+                    return Err(err(&format!("invalid digest `{}`", digest)));
+                }
+                if let Some(hex) = digest.strip_prefix("sha256:") {
+                    if hex.len() != 64 || hex.chars().any(|c| c.is_ascii_uppercase()) {
+                        return Err(err("sha256 digest has to be 64 lowercase hex characters"));
+                    }
+                }
+                (name_and_tag, Some(digest.to_string()))
+            }
+            None => (reference, None),
+        };
+
+        // `:` after the last `/` separates tag, otherwise it's a port of registry
+        let (name, tag) = match name_and_tag.rfind(':') {
+            Some(index) if !name_and_tag[index..].contains('/') => {
+                let tag = &name_and_tag[index + 1..];
+                if !TAG.is_match(tag) {
+                    return Err(err(&format!("invalid tag `{}`", tag)));
+                }
+                (&name_and_tag[..index], Some(tag.to_string()))
+            }
+            _ => (name_and_tag, None),
+        };
+        if name.is_empty() {
+            return Err(err("empty name"));
+        }
+        if name.len() > NAME_TOTAL_LENGTH_MAX {
+            return Err(err(&format!(
+                "name is longer than {} characters",
+                NAME_TOTAL_LENGTH_MAX
+            )));
+        }
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((first, rest))
+                if first.contains('.') || first.contains(':') || first == "localhost" =>
+            {
+                if !DOMAIN.is_match(first) {
+                    return Err(err(&format!("invalid registry `{}`", first)));
+                }
+                (first, rest.to_string())
+            }
+            _ => (DEFAULT_REGISTRY, name.to_string()),
+        };
+        let registry = match registry {
+            LEGACY_DEFAULT_REGISTRY => DEFAULT_REGISTRY,
+            registry => registry,
+        };
+        if let Some(component) = repository
+            .split('/')
+            .find(|component| !PATH_COMPONENT.is_match(component))
+        {
+            return Err(err(&format!(
+                "invalid repository path component `{}`",
+                component
+            )));
+        }
+        let repository = if registry == DEFAULT_REGISTRY && !repository.contains('/') {
+            format!("{}{}", OFFICIAL_REPOSITORY_PREFIX, repository)
+        } else {
+            repository
+        };
+
+        Ok(Self {
+            registry: registry.to_string(),
+            repository,
+            tag,
+            digest,
+        })
+    }
+}
+
+impl ImageReference {
+    /// normalized `registry/repository`
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.registry, self.repository)
+    }
+
+    /// shortest form of [Self::name], as it's displayed by `docker` CLI
+    pub fn familiar_name(&self) -> String {
+        if self.registry != DEFAULT_REGISTRY {
+            return self.name();
+        }
+        self.repository
+            .strip_prefix(OFFICIAL_REPOSITORY_PREFIX)
+            .unwrap_or(&self.repository)
+            .to_string()
+    }
+
+    /// hex part of `sha256:<hex>` digest
+    pub fn sha256_digest(&self) -> Option<&str> {
+        self.digest.as_deref()?.strip_prefix("sha256:")
+    }
+
+    /// normalized `registry/repository[:tag]`, without digest
+    pub fn name_and_tag(&self) -> String {
+        match self.tag {
+            Some(ref tag) => format!("{}:{}", self.name(), tag),
+            None => self.name(),
+        }
+    }
+}
+
+impl std::fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name_and_tag())?;
+        if let Some(ref digest) = self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImageReference;
+
+    const DIGEST: &str = "sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+
+    #[test]
+    fn test_parse() -> eyre::Result<()> {
+        let reference: ImageReference =
+            format!("sourcescan/cargo-near:0.13.4-rust-1.85.0@{}", DIGEST).parse()?;
+        assert_eq!(
+            reference,
+            ImageReference {
+                registry: "docker.io".into(),
+                repository: "sourcescan/cargo-near".into(),
+                tag: Some("0.13.4-rust-1.85.0".into()),
+                digest: Some(DIGEST.into()),
+            }
+        );
+        assert_eq!(reference.familiar_name(), "sourcescan/cargo-near");
+        assert_eq!(
+            reference.to_string(),
+            format!(
+                "docker.io/sourcescan/cargo-near:0.13.4-rust-1.85.0@{}",
+                DIGEST
+            )
+        );
+
+        let reference: ImageReference =
+            format!("localhost:5000/cargo-near:tag@{}", DIGEST).parse()?;
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.repository, "cargo-near");
+        assert_eq!(reference.tag.as_deref(), Some("tag"));
+        assert_eq!(reference.familiar_name(), "localhost:5000/cargo-near");
+
+        let reference: ImageReference = "localhost:5000/cargo-near".parse()?;
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.tag, None);
+
+        let reference: ImageReference = format!("ubuntu@{}", DIGEST).parse()?;
+        assert_eq!(reference.name(), "docker.io/library/ubuntu");
+        assert_eq!(reference.familiar_name(), "ubuntu");
+        assert_eq!(reference.sha256_digest(), DIGEST.strip_prefix("sha256:"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalization() -> eyre::Result<()> {
+        let names = [
+            "sourcescan/cargo-near",
+            "docker.io/sourcescan/cargo-near",
+            "index.docker.io/sourcescan/cargo-near",
+        ];
+        for name in names {
+            assert_eq!(
+                name.parse::<ImageReference>()?.name(),
+                "docker.io/sourcescan/cargo-near"
+            );
+        }
+        assert_eq!(
+            "docker.io/ubuntu".parse::<ImageReference>()?,
+            "library/ubuntu".parse::<ImageReference>()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        for reference in [
+            "",
+            " sourcescan/cargo-near",
+            "sourcescan/Cargo-near",
+            "sourcescan/cargo-near:",
+            "sourcescan/cargo-near:0.13.4-rüst",
+            "sourcescan/cargo-near@sha256:abc",
+            "sourcescan//cargo-near",
+            "-registry.io/cargo-near",
+            "sourcescan/cargo-near:tag@sha256:A9D8BEE7B134856CC8BAA142494A177F2BA9ECFEDEDFCDD38F634E14CCA8AAE2",
+        ] {
+            assert!(
+                reference.parse::<ImageReference>().is_err(),
+                "`{}` expected to be invalid",
+                reference
+            );
+        }
+    }
+}
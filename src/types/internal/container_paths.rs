@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use eyre::ContextCompat;
 
-use crate::{logic::NEP330_REPO_MOUNT, types::contract_source_metadata::BuildInfo};
+use crate::types::contract_source_metadata::BuildInfo;
 
 pub struct Paths {
     pub host_volume_arg: String,
@@ -12,14 +12,15 @@ pub struct Paths {
 impl Paths {
     pub fn compute(
         build_info_mixed: &BuildInfo,
-        contract_source_workdir: camino::Utf8PathBuf,
+        repo_mount: &str,
+        host_workdir: camino::Utf8PathBuf,
     ) -> eyre::Result<Self> {
-        let mounted_repo = NEP330_REPO_MOUNT.to_string();
+        let mounted_repo = repo_mount.to_string();
         // `:z` requests an SELinux shared relabel of the bind mount, needed on
         // enforcing hosts (Fedora/RHEL) and ignored by Docker/Podman elsewhere.
-        let host_volume_arg = format!("{}:{}:z", contract_source_workdir.as_str(), &mounted_repo);
+        let host_volume_arg = format!("{}:{}:z", host_workdir.as_str(), &mounted_repo);
         let crate_path = {
-            let mut repo_path = unix_path::Path::new(NEP330_REPO_MOUNT).to_path_buf();
+            let mut repo_path = unix_path::Path::new(repo_mount).to_path_buf();
             let relative_crate_path =
                 unix_path::PathBuf::from_str(&build_info_mixed.contract_path)?;
             repo_path.push(relative_crate_path);
@@ -61,7 +61,7 @@ impl CrateMetadata {
         let result = self.target_directory.clone();
         tracing::info!(
             target: "near_teach_me",
-            parent: &tracing::Span::none(),
+            output_dir = %result,
             "Resolved output directory: {}", result
         );
         Ok(result)
@@ -79,13 +79,13 @@ impl CrateMetadata {
     }
 }
 /// Get the result of `cargo metadata`, together with the root package id.
-fn get_cargo_metadata(
+pub(crate) fn get_cargo_metadata(
     manifest_path: &ManifestPath,
     no_locked: bool,
 ) -> eyre::Result<(cargo_metadata::Metadata, Package)> {
     tracing::info!(
         target: "near_teach_me",
-        parent: &tracing::Span::none(),
+        manifest_path = %manifest_path.path,
         "Fetching cargo metadata for {}", manifest_path.path
     );
     let mut cmd = MetadataCommand::new();
@@ -95,7 +95,6 @@ fn get_cargo_metadata(
     let cmd = cmd.manifest_path(&manifest_path.path);
     tracing::info!(
         target: "near_teach_me",
-        parent: &tracing::Span::none(),
         "Command execution:\n{}",
         pretty_print::indent_payload(&format!("{:#?}", cmd.cargo_command()))
     );
@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SHA256Checksum {
     pub hash: Vec<u8>,
 }
@@ -7,7 +7,8 @@ use url::Url;
 /// Cargo. It's to help paper over mistakes such as depending on
 /// `github.com/foo/bar` vs `github.com/foo/bar.git`. This is **only** for
 /// internal purposes within Cargo and provides no means to actually read the
-/// underlying string value of the `Url` it contains. This is intentional,
+/// underlying string value of the `Url` it contains, besides a [std::fmt::Display]
+/// for diagnostics (logs, tracing spans). This is intentional,
 /// because all fetching should still happen within the context of the original
 /// URL.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -57,3 +58,9 @@ impl CanonicalUrl {
         Ok(CanonicalUrl(url))
     }
 }
+
+impl std::fmt::Display for CanonicalUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
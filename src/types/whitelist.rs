@@ -1,8 +1,307 @@
+use std::collections::BTreeMap;
+
+use cargo_metadata::semver::Version;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::logic::platform::Platform;
+use crate::types::build_environment_info::ToolchainPolicy;
+use crate::types::image_reference::ImageReference;
+
 pub type Whitelist = Vec<WhitelistEntry>;
 
 #[derive(Debug, Clone, PartialEq, Default, Eq, Serialize, Deserialize)]
 pub struct WhitelistEntry {
+    /// image reference, whose normalized name has to match; its tag and digest have to match too,
+    /// if they're specified; empty if [Self::repository_pattern] is set instead
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub expected_docker_image: String,
+    /// pattern of normalized `registry/repository` name, e.g. `{"glob": "registry.internal:5000/near/*"}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository_pattern: Option<RepositoryPattern>,
+    #[serde(default, skip_serializing_if = "EntryKind::is_allow")]
+    pub kind: EntryKind,
+    /// entry applies to verifications evaluated at or after this time, e.g. `2025-03-01T00:00:00Z`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<DateTime<Utc>>,
+    /// entry applies to verifications evaluated before this time, so that verifications
+    /// done earlier can still be replayed against it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<DateTime<Utc>>,
+    /// reason of revocation; a revoked entry doesn't apply at any time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked: Option<String>,
+    /// entry applies to metadata with `nep330` standard of at least this version, e.g. `1.2.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_nep330_version: Option<String>,
+    /// entry applies to images, whose tag encodes toolchain versions within these bounds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<ToolchainPolicy>,
+    /// repository mount point inside of the image's container, [crate::logic::NEP330_REPO_MOUNT] if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_mount: Option<String>,
+    /// platform of the image to pull and run, e.g. `linux/amd64`; if not set, it's the platform
+    /// of the pulled image, see [crate::logic::platform]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    /// names of public keys, each of which has to have signed the image; checked by
+    /// [crate::logic::nep330_build::run_opts] with `docker_checks::signature::verify`,
+    /// which requires `signature` feature
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_signers: Vec<String>,
+    /// labels the image has to carry, e.g. `{"org.opencontainers.image.source": "github.com/near/cargo-near"}`,
+    /// see [crate::logic::docker_checks::labels::check]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub expected_labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    #[default]
+    Allow,
+    /// matching images are rejected, regardless of any allow entries
+    Deny,
+}
+
+impl EntryKind {
+    fn is_allow(&self) -> bool {
+        *self == Self::Allow
+    }
+}
+
+/// matched against the whole of [ImageReference::name], e.g. `docker.io/sourcescan/cargo-near`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepositoryPattern {
+    /// `*` and `?` don't match `/`, `**` matches anything
+    Glob(String),
+    Regex(String),
+}
+
+impl RepositoryPattern {
+    fn to_regex(&self) -> eyre::Result<regex::Regex> {
+        let pattern = match self {
+            Self::Glob(glob) => {
+                let mut pattern = String::new();
+                let mut chars = glob.chars().peekable();
+                while let Some(char) = chars.next() {
+                    match char {
+                        '*' if chars.peek() == Some(&'*') => {
+                            chars.next();
+                            pattern.push_str(".*");
+                        }
+                        '*' => pattern.push_str("[^/]*"),
+                        '?' => pattern.push_str("[^/]"),
+                        char => pattern.push_str(&regex::escape(&char.to_string())),
+                    }
+                }
+                pattern
+            }
+            Self::Regex(regex) => regex.clone(),
+        };
+        regex::Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|err| eyre::eyre!("invalid repository pattern {:?}: {}", self, err))
+    }
+}
+
+impl WhitelistEntry {
+    pub fn matches(&self, image: &ImageReference) -> eyre::Result<bool> {
+        match (
+            self.expected_docker_image.is_empty(),
+            &self.repository_pattern,
+        ) {
+            (false, None) => {
+                let expected: ImageReference = self.expected_docker_image.parse()?;
+                Ok(expected.name() == image.name()
+                    && (expected.tag.is_none() || expected.tag == image.tag)
+                    && (expected.digest.is_none() || expected.digest == image.digest))
+            }
+            (true, Some(pattern)) => Ok(pattern.to_regex()?.is_match(&image.name())),
+            _ => Err(eyre::eyre!(
+                "whitelist entry {} has to specify exactly one of `expected_docker_image` \
+                and `repository_pattern`",
+                self
+            )),
+        }
+    }
+}
+
+impl WhitelistEntry {
+    /// [None] if the entry applies to a verification, evaluated `at`, of metadata
+    /// with `nep330_version`; the reason it doesn't otherwise
+    pub fn inactive_reason(
+        &self,
+        at: DateTime<Utc>,
+        nep330_version: Option<&Version>,
+    ) -> eyre::Result<Option<String>> {
+        if let Some(ref reason) = self.revoked {
+            return Ok(Some(format!("revoked: {}", reason)));
+        }
+        if let Some(valid_from) = self.valid_from {
+            if at < valid_from {
+                return Ok(Some(format!("not valid until {}", valid_from.to_rfc3339())));
+            }
+        }
+        if let Some(valid_until) = self.valid_until {
+            if at >= valid_until {
+                return Ok(Some(format!("expired at {}", valid_until.to_rfc3339())));
+            }
+        }
+        if let Some(ref min_nep330_version) = self.min_nep330_version {
+            let min_nep330_version = Version::parse(min_nep330_version).map_err(|err| {
+                eyre::eyre!(
+                    "invalid `min_nep330_version` `{}` of whitelist entry {}: {}",
+                    min_nep330_version,
+                    self,
+                    err
+                )
+            })?;
+            match nep330_version {
+                Some(version) if *version >= min_nep330_version => {}
+                Some(version) => {
+                    return Ok(Some(format!(
+                        "requires nep330 {} or newer, metadata declares {}",
+                        min_nep330_version, version
+                    )));
+                }
+                None => {
+                    return Ok(Some(format!(
+                        "requires nep330 {} or newer, metadata declares no nep330 version",
+                        min_nep330_version
+                    )));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Curated list of known-good `sourcescan/cargo-near` images, embedded into the crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CuratedWhitelist {
+    /// incremented on every change of [Self::entries]
+    pub version: u32,
+    pub entries: Vec<CuratedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CuratedEntry {
+    #[serde(flatten)]
+    pub entry: WhitelistEntry,
+    pub cargo_near: Version,
+    pub rustc: Version,
+}
+
+impl CuratedWhitelist {
+    pub fn embedded() -> Self {
+        serde_json::from_str(include_str!("whitelist/curated.json"))
+            .expect("embedded curated whitelist is valid")
+    }
+
+    pub fn whitelist(&self) -> Whitelist {
+        self.entries
+            .iter()
+            .map(|curated| curated.entry.clone())
+            .collect()
+    }
+}
+
+/// Which images [crate::types::contract_source_metadata::ContractSourceMetadata::validate_with] accepts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum WhitelistPolicy {
+    /// [CuratedWhitelist::embedded]
+    #[default]
+    Curated,
+    Custom(Whitelist),
+    /// any image, pinned with a digest; an explicit opt-out of whitelisting
+    AnyImage,
+}
+
+impl WhitelistPolicy {
+    pub fn whitelist(&self) -> Option<Whitelist> {
+        match self {
+            Self::Curated => Some(CuratedWhitelist::embedded().whitelist()),
+            Self::Custom(whitelist) => Some(whitelist.clone()),
+            Self::AnyImage => None,
+        }
+    }
+}
+
+impl std::fmt::Display for WhitelistEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            EntryKind::Allow => "allow",
+            EntryKind::Deny => "deny",
+        };
+        match self.repository_pattern {
+            Some(RepositoryPattern::Glob(ref glob)) => write!(f, "{} glob `{}`", kind, glob),
+            Some(RepositoryPattern::Regex(ref regex)) => write!(f, "{} regex `{}`", kind, regex),
+            None => write!(f, "{} `{}`", kind, self.expected_docker_image),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CuratedWhitelist, Platform, RepositoryPattern, WhitelistEntry};
+    use crate::types::build_environment_info::BuildEnvironmentInfo;
+
+    #[test]
+    fn test_curated_whitelist() -> eyre::Result<()> {
+        let curated = CuratedWhitelist::embedded();
+        assert!(!curated.entries.is_empty());
+        for curated in curated.entries {
+            let info: BuildEnvironmentInfo = curated.entry.expected_docker_image.parse()?;
+            assert!(info.image.sha256_digest().is_some());
+            assert_eq!(info.image.familiar_name(), "sourcescan/cargo-near");
+            assert_eq!(info.cargo_near.as_ref(), Some(&curated.cargo_near));
+            assert_eq!(info.rustc.as_ref(), Some(&curated.rustc));
+            assert_eq!(curated.entry.platform, Some(Platform::linux_amd64()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_repository_pattern() -> eyre::Result<()> {
+        let image = "registry.internal:5000/near/cargo-near:tag".parse()?;
+        let entry = |pattern| WhitelistEntry {
+            repository_pattern: Some(pattern),
+            ..Default::default()
+        };
+
+        assert!(
+            entry(RepositoryPattern::Glob(
+                "registry.internal:5000/near/*".into()
+            ))
+            .matches(&image)?
+        );
+        assert!(
+            entry(RepositoryPattern::Glob("registry.internal:5000/**".into())).matches(&image)?
+        );
+        assert!(
+            !entry(RepositoryPattern::Glob("registry.internal:5000/*".into())).matches(&image)?
+        );
+        assert!(
+            !entry(RepositoryPattern::Glob(
+                "registry.internal:5000/near".into()
+            ))
+            .matches(&image)?
+        );
+        assert!(
+            entry(RepositoryPattern::Regex(
+                r"registry\.internal:5000/near/cargo-.+".into()
+            ))
+            .matches(&image)?
+        );
+        assert!(!entry(RepositoryPattern::Regex("near/cargo-near".into())).matches(&image)?);
+
+        let both = WhitelistEntry {
+            expected_docker_image: "near/cargo-near".into(),
+            repository_pattern: Some(RepositoryPattern::Glob("**".into())),
+            ..Default::default()
+        };
+        assert!(both.matches(&image).is_err());
+        Ok(())
+    }
 }
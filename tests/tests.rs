@@ -10,9 +10,13 @@ struct TestCase {
 fn common_verify_test_routine_opts(
     test_case: TestCase,
     whitelist: Option<Whitelist>,
+    opts: near_verify_rs::logic::nep330_build::Opts,
 ) -> eyre::Result<()> {
     let contract_source_metadata: ContractSourceMetadata = serde_json::from_str(test_case.input)?;
 
+    let verify_span = near_verify_rs::logic::spans::verify(&contract_source_metadata);
+    let _verify_guard = verify_span.enter();
+    let verify_started = std::time::Instant::now();
     assert!(contract_source_metadata.build_info.is_some());
     let source_id = near_verify_rs::types::source_id::SourceId::from_url(
         &contract_source_metadata
@@ -22,17 +26,26 @@ fn common_verify_test_routine_opts(
             .source_code_snapshot,
     )?;
 
-    let (_tempdir, target_dir) = checkout::checkout(source_id)?;
+    let (_tempdir, target_dir) = {
+        let checkout_span = near_verify_rs::logic::spans::checkout(&source_id);
+        let _checkout_guard = checkout_span.enter();
+        let checkout_started = std::time::Instant::now();
+        let checked_out = checkout::checkout(source_id)?;
+        near_verify_rs::logic::spans::record_duration(&checkout_span, checkout_started);
+        checked_out
+    };
 
     let target_dir = camino::Utf8PathBuf::from_path_buf(target_dir)
         .map_err(|err| eyre::eyre!("convert path buf {:?}", err))?;
 
-    contract_source_metadata.validate(whitelist)?;
-    let docker_build_out_wasm = near_verify_rs::logic::nep330_build::run(
+    let whitelist_entry = contract_source_metadata.validate(whitelist)?;
+    let docker_build_out_wasm = near_verify_rs::logic::nep330_build::run_opts(
         contract_source_metadata,
         target_dir,
-        vec![],
-        false,
+        near_verify_rs::logic::nep330_build::Opts {
+            whitelist_entry,
+            ..opts
+        },
     )?;
 
     let result = near_verify_rs::logic::compute_hash(docker_build_out_wasm)?;
@@ -42,11 +55,12 @@ fn common_verify_test_routine_opts(
         test_case.expected_output,
         "Artifact hash-sum mismatch"
     );
+    near_verify_rs::logic::spans::record_duration(&verify_span, verify_started);
 
     Ok(())
 }
 fn common_verify_test_routine(test_case: TestCase) -> eyre::Result<()> {
-    common_verify_test_routine_opts(test_case, None)
+    common_verify_test_routine_opts(test_case, None, Default::default())
 }
 
 /// https://testnet.nearblocks.io/address/simple-package-verify-rs-ci.testnet?tab=contract
@@ -84,6 +98,21 @@ fn test_simple_package_vanilla() -> eyre::Result<()> {
     Ok(())
 }
 
+/// source is copied into a created container and output wasm is copied out of it,
+/// rather than bind-mounted
+#[test]
+fn test_simple_package_vanilla_copy_in_out() -> eyre::Result<()> {
+    common_verify_test_routine_opts(
+        SIMPLE_PACKAGE_VANILLA,
+        None,
+        near_verify_rs::logic::nep330_build::Opts {
+            transfer: near_verify_rs::logic::nep330_build::Transfer::CopyInOut,
+            ..Default::default()
+        },
+    )?;
+    Ok(())
+}
+
 /// https://testnet.nearblocks.io/address/simple-package-with-features-verify-rs-ci.testnet
 /// https://github.com/dj8yfo/verify_contracts_collection/releases/tag/simple-package-with-features-v1.0.0
 const SIMPLE_PACKAGE_WITH_FEATURES: TestCase = TestCase {
@@ -704,7 +733,8 @@ fn test_simple_factory_product_with_out_path_with_passed_env() -> eyre::Result<(
 mod whitelist {
 
     use near_verify_rs::types::{
-        contract_source_metadata::ContractSourceMetadata, whitelist::Whitelist,
+        contract_source_metadata::ContractSourceMetadata,
+        whitelist::{Whitelist, WhitelistPolicy},
     };
 
     use crate::TestCase;
@@ -750,12 +780,98 @@ mod whitelist {
         Ok(())
     }
 
+    /// this test case is not checked out or compiled, only metadata validated
+    const CONTRACT_WITH_CUSTOM_REPO_MOUNT: TestCase = TestCase {
+        input: r#"{
+  "build_info": {
+    "build_command": [
+      "cargo",
+      "near",
+      "build",
+      "non-reproducible-wasm",
+      "--locked"
+    ],
+    "build_environment": "dj8yfo/sourcescan:0.x.x-dev-pr-262@sha256:a231d4bf975d561a06dd5357f2ac03c883e8b3b510994f3b40c9b975dcdb02ce",
+    "contract_path": "",
+    "output_wasm_path": "/workspace/code/target/near/simple_package.wasm",
+    "source_code_snapshot": "git+https://github.com/dj8yfo/verify_contracts_collection?rev=cb100096d0eb67654857949e1ff49fff2f385012"
+  },
+  "link": "https://github.com/dj8yfo/verify_contracts_collection/tree/cb100096d0eb67654857949e1ff49fff2f385012",
+  "standards": [
+    {
+      "standard": "nep330",
+      "version": "1.3.0"
+    }
+  ],
+  "version": "1.0.0"
+}"#,
+        expected_output: "NNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNNN",
+    };
+
+    #[test]
+    fn test_simple_package_with_custom_repo_mount() -> eyre::Result<()> {
+        let whitelist: Whitelist = {
+            let file = std::fs::read("tests/resources/whitelist_ok_custom_repo_mount.json")
+                .expect("no std:fs::read error");
+            serde_json::from_slice(&file).expect("no serde_json::from_slice error")
+        };
+        let contract_source_metadata: ContractSourceMetadata =
+            serde_json::from_str(CONTRACT_WITH_CUSTOM_REPO_MOUNT.input)?;
+
+        let entry = contract_source_metadata.validate(Some(whitelist))?;
+        assert_eq!(
+            entry.and_then(|entry| entry.repo_mount).as_deref(),
+            Some("/workspace/code")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_packages_on_curated_whitelist() -> eyre::Result<()> {
+        for test_case in [
+            &crate::SIMPLE_PACKAGE_VANILLA,
+            &crate::SIMPLE_PACKAGE_WITH_OUT_PATH,
+            &crate::SIMPLE_FACTORY_WITH_OUT_PATH,
+        ] {
+            let contract_source_metadata: ContractSourceMetadata =
+                serde_json::from_str(test_case.input)?;
+            let entry = contract_source_metadata
+                .validate_with(&WhitelistPolicy::default(), chrono::Utc::now())?;
+            assert!(entry.is_some());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_package_replayed_before_expiry() -> eyre::Result<()> {
+        let whitelist: Whitelist = {
+            let file = std::fs::read("tests/resources/whitelist_ok_expired_image.json")
+                .expect("no std:fs::read error");
+            serde_json::from_slice(&file).expect("no serde_json::from_slice error")
+        };
+        let contract_source_metadata: ContractSourceMetadata =
+            serde_json::from_str(CONTRACT_WITH_NONSTANDARD_IMAGE.input)?;
+
+        contract_source_metadata
+            .validate_at(Some(whitelist.clone()), "2025-05-01T00:00:00Z".parse()?)?;
+
+        let Err(err) = contract_source_metadata.validate(Some(whitelist)) else {
+            panic!("Expecting an error returned from `contract_source_metadata.validate`");
+        };
+        assert!(format!("{:?}", err).contains("expired at"));
+        Ok(())
+    }
+
     mod decline {
         use near_verify_rs::types::{
-            contract_source_metadata::ContractSourceMetadata, whitelist::Whitelist,
+            contract_source_metadata::ContractSourceMetadata,
+            whitelist::{Whitelist, WhitelistPolicy},
         };
 
-        use crate::{TestCase, whitelist::CONTRACT_WITH_NONSTANDARD_IMAGE};
+        use crate::{
+            TestCase,
+            whitelist::{CONTRACT_WITH_CUSTOM_REPO_MOUNT, CONTRACT_WITH_NONSTANDARD_IMAGE},
+        };
 
         #[test]
         fn test_decline_simple_package_with_unexpected_image() -> eyre::Result<()> {
@@ -777,6 +893,43 @@ mod whitelist {
             Ok(())
         }
 
+        #[test]
+        fn test_decline_simple_package_with_denied_digest() -> eyre::Result<()> {
+            let whitelist: Whitelist = {
+                let file = std::fs::read("tests/resources/whitelist_err_denied_digest.json")
+                    .expect("no std:fs::read error");
+                serde_json::from_slice(&file).expect("no serde_json::from_slice error")
+            };
+
+            let contract_source_metadata: ContractSourceMetadata =
+                serde_json::from_str(CONTRACT_WITH_NONSTANDARD_IMAGE.input)?;
+
+            let Err(err) = contract_source_metadata.validate(Some(whitelist)) else {
+                panic!("Expecting an error returned from `contract_source_metadata.validate`");
+            };
+            println!("{:#?}", err);
+
+            assert!(format!("{:?}", err).contains("is denied by whitelist entry #1"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_decline_simple_package_with_nonstandard_image_on_curated_whitelist()
+        -> eyre::Result<()> {
+            let contract_source_metadata: ContractSourceMetadata =
+                serde_json::from_str(CONTRACT_WITH_NONSTANDARD_IMAGE.input)?;
+
+            let Err(err) = contract_source_metadata
+                .validate_with(&WhitelistPolicy::Curated, chrono::Utc::now())
+            else {
+                panic!("Expecting an error returned from `contract_source_metadata.validate_with`");
+            };
+            println!("{:#?}", err);
+
+            assert!(format!("{:?}", err).contains("no matching entry found for"));
+            Ok(())
+        }
+
         /// this test case is not checked out or compiled, only metadata validated
         const SIMPLE_PACKAGE_WITH_INVALID_OUT_PATH: TestCase = TestCase {
             input: r#"{
@@ -806,10 +959,35 @@ mod whitelist {
         };
         #[test]
         fn test_decline_simple_package_with_invalid_out_path() -> eyre::Result<()> {
+            let whitelist: Whitelist = {
+                let file = std::fs::read("tests/resources/whitelist_ok_nonstandard_image.json")
+                    .expect("no std:fs::read error");
+                serde_json::from_slice(&file).expect("no serde_json::from_slice error")
+            };
             let contract_source_metadata: ContractSourceMetadata =
                 serde_json::from_str(SIMPLE_PACKAGE_WITH_INVALID_OUT_PATH.input)?;
 
-            let Err(err) = contract_source_metadata.validate(None) else {
+            let Err(err) = contract_source_metadata.validate(Some(whitelist)) else {
+                panic!("Expecting an error returned from `contract_source_metadata.validate`");
+            };
+            println!("{:#?}", err);
+
+            assert!(format!("{:?}", err).contains("isn't a subpath of `/home/near/code`"));
+            Ok(())
+        }
+
+        #[test]
+        fn test_decline_simple_package_with_custom_repo_mount_on_default_whitelist()
+        -> eyre::Result<()> {
+            let whitelist: Whitelist = {
+                let file = std::fs::read("tests/resources/whitelist_ok_nonstandard_image.json")
+                    .expect("no std:fs::read error");
+                serde_json::from_slice(&file).expect("no serde_json::from_slice error")
+            };
+            let contract_source_metadata: ContractSourceMetadata =
+                serde_json::from_str(CONTRACT_WITH_CUSTOM_REPO_MOUNT.input)?;
+
+            let Err(err) = contract_source_metadata.validate(Some(whitelist)) else {
                 panic!("Expecting an error returned from `contract_source_metadata.validate`");
             };
             println!("{:#?}", err);
@@ -819,3 +997,79 @@ mod whitelist {
         }
     }
 }
+
+#[test]
+fn test_reap_stale_containers() -> eyre::Result<()> {
+    use near_verify_rs::logic::container_cleanup;
+
+    const IMAGE: &str = "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+    let host_id = container_cleanup::host_id().expect("host id of a test host");
+    // pid of an exited process
+    let mut child = std::process::Command::new("true").spawn()?;
+    let dead_pid = child.id();
+    child.wait()?;
+
+    let name = format!("near-verify-rs-reap-test-{}", dead_pid);
+    let status = std::process::Command::new("docker")
+        .args(["create", "--name", &name])
+        .args([
+            "--label",
+            &format!("{}={}", container_cleanup::PID, dead_pid),
+        ])
+        .args([
+            "--label",
+            &format!("{}={}", container_cleanup::HOST, host_id),
+        ])
+        .args([IMAGE, "true"])
+        .status()?;
+    assert!(status.success());
+
+    let reaped = container_cleanup::reap_stale_containers(
+        &Default::default(),
+        &near_verify_rs::reporter::Silent,
+    )?;
+    assert!(reaped.removed.contains(&name));
+    assert!(reaped.failed.is_empty());
+    let inspect = std::process::Command::new("docker")
+        .args(["container", "inspect", &name])
+        .output()?;
+    assert!(!inspect.status.success());
+    Ok(())
+}
+
+/// image is copied from registry into an archive with its manifests kept intact,
+/// removed locally and loaded from the archive, so that the build needs no registry
+#[test]
+fn test_simple_package_vanilla_loaded_image() -> eyre::Result<()> {
+    use near_verify_rs::logic::docker_checks::load_image;
+
+    const IMAGE: &str = "sourcescan/cargo-near:0.13.4-rust-1.85.0@sha256:a9d8bee7b134856cc8baa142494a177f2ba9ecfededfcdd38f634e14cca8aae2";
+    let tempdir = tempfile::tempdir()?;
+    let archive = tempdir.path().join("image.tar");
+    let status = std::process::Command::new("skopeo")
+        .args(["copy", "--multi-arch", "all"])
+        .arg(format!("docker://{}", IMAGE))
+        .arg(format!("oci-archive:{}", archive.display()))
+        .status()?;
+    assert!(status.success());
+    // the image may be present after other tests
+    std::process::Command::new("docker")
+        .args(["image", "rm", "--force", IMAGE])
+        .status()?;
+
+    let loaded = load_image::check_opts(
+        &archive,
+        IMAGE,
+        &load_image::Opts {
+            platform: Some(near_verify_rs::logic::platform::Platform::linux_amd64()),
+            ..Default::default()
+        },
+        &near_verify_rs::reporter::Silent,
+    )?;
+    common_verify_test_routine_opts(
+        SIMPLE_PACKAGE_VANILLA,
+        None,
+        load_image::build_opts(&loaded),
+    )?;
+    Ok(())
+}